@@ -1,46 +1,288 @@
-#[cfg(not(feature = "std"))]
-use alloc::String;
+use core::fmt;
 
-#[derive(Debug, PartialEq)]
+/// Kind of error reported by a Dynamixel controller.
+#[derive(Clone, Debug, PartialEq)]
 pub enum ErrorType {
+    /// The received bytes are not a valid StatusPacket.
     Parsing,
+    /// The register is not supported (e.g. unsupported data length).
     UnsupportedRegister,
+    /// The motor answered with this error code.
     StatusError(u8),
+    /// The motor did not understand the instruction (undefined instruction or sent out of sequence).
+    ///
+    /// This points at the packet built by the controller (or a custom instruction) rather than at the bus or the motor.
+    InstructionError,
+    /// The instruction was processed but the motor has a hardware error latched (details in its HardwareErrorStatus register).
+    Alert,
+    /// The CRC of the received StatusPacket does not match its content.
     InvalidChecksum,
-    Timeout,
+    /// The StatusPacket was not received in time.
+    ///
+    /// `received` is the number of bytes received before the timeout: none usually means the motor is absent or uses another baud rate, while a partial packet means the timeout is too short.
+    Timeout {
+        /// Number of bytes received (including skipped stray bytes)
+        received: usize,
+    },
+    /// The value can not be sent to the motor (e.g. out of range or unsupported by the model).
+    InvalidValue,
+    /// The serial transport reported an error.
+    Serial(SerialErrorKind),
+    /// The value read back after a write does not match the written value.
+    VerificationFailed,
+    /// The EEPROM register can not be written while the torque of the motor is enabled.
+    TorqueMustBeDisabled,
+    /// The motor was still moving when the wait for it to stop timed out.
+    ///
+    /// Contrary to a `Timeout`, the motor answered all along.
+    StillMoving,
+    /// The motor did not reach its goal position in time (e.g. it is stalled).
+    ///
+    /// Contrary to a `Timeout`, the motor answered all along.
+    NotInPosition,
 }
 
-#[derive(Debug)]
+impl ErrorType {
+    /// Short description of the error, without allocating (e.g. to report it from a no_std control loop).
+    ///
+    /// The details of the error (status error code, received bytes, serial error kind) are not included, see the `Debug` rendering for them.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorType::Parsing => "invalid StatusPacket",
+            ErrorType::UnsupportedRegister => "unsupported register",
+            ErrorType::StatusError(_) => "status error",
+            ErrorType::InstructionError => "instruction error",
+            ErrorType::Alert => "hardware error alert",
+            ErrorType::InvalidChecksum => "invalid checksum",
+            ErrorType::Timeout { .. } => "timeout",
+            ErrorType::InvalidValue => "invalid value",
+            ErrorType::Serial(_) => "serial error",
+            ErrorType::VerificationFailed => "verification failed",
+            ErrorType::TorqueMustBeDisabled => "torque must be disabled",
+            ErrorType::StillMoving => "still moving",
+            ErrorType::NotInPosition => "not in position",
+        }
+    }
+    /// Stable numeric code of the error, e.g. to report it through a C API (0 is left for success).
+    ///
+    /// | Error | Code |
+    /// |-------|------|
+    /// | `Parsing` | 1 |
+    /// | `UnsupportedRegister` | 2 |
+    /// | `Alert` | 3 |
+    /// | `InvalidChecksum` | 4 |
+    /// | `Timeout` | 5 |
+    /// | `InvalidValue` | 6 |
+    /// | `VerificationFailed` | 7 |
+    /// | `TorqueMustBeDisabled` | 8 |
+    /// | `InstructionError` | 9 |
+    /// | `StillMoving` | 10 |
+    /// | `NotInPosition` | 11 |
+    /// | `StatusError(e)` | 0x100 + e |
+    /// | `Serial(kind)` | 0x200 + kind (`Framing` 0, `Noise` 1, `Overrun` 2, `Parity` 3, `Other` 4, `TxTimeout` 5) |
+    ///
+    /// The number of bytes received before a timeout is not part of the code.
+    pub fn as_code(&self) -> i32 {
+        match *self {
+            ErrorType::Parsing => 1,
+            ErrorType::UnsupportedRegister => 2,
+            ErrorType::Alert => 3,
+            ErrorType::InvalidChecksum => 4,
+            ErrorType::Timeout { .. } => 5,
+            ErrorType::InvalidValue => 6,
+            ErrorType::VerificationFailed => 7,
+            ErrorType::TorqueMustBeDisabled => 8,
+            ErrorType::InstructionError => 9,
+            ErrorType::StillMoving => 10,
+            ErrorType::NotInPosition => 11,
+            ErrorType::StatusError(e) => 0x100 + i32::from(e),
+            ErrorType::Serial(kind) => {
+                0x200
+                    + match kind {
+                        SerialErrorKind::Framing => 0,
+                        SerialErrorKind::Noise => 1,
+                        SerialErrorKind::Overrun => 2,
+                        SerialErrorKind::Parity => 3,
+                        SerialErrorKind::Other => 4,
+                        SerialErrorKind::TxTimeout => 5,
+                    }
+            }
+        }
+    }
+}
+
+/// Kind of error reported by the serial transport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SerialErrorKind {
+    /// A stop bit was not received where expected.
+    Framing,
+    /// Noise was detected on the line.
+    Noise,
+    /// A received byte was lost because the previous one was not read in time.
+    Overrun,
+    /// The parity check failed.
+    Parity,
+    /// Any other transport error.
+    Other,
+    /// The transmitter did not accept a byte (or finish sending) in time, e.g. it is wedged.
+    TxTimeout,
+}
+
+/// Serial errors that can be reported by a Dynamixel controller.
+///
+/// It must be implemented for the error type of the serial RX/TX (wrapping the serial if needed) so transport errors are reported as `ErrorType::Serial`.
+pub trait SerialError {
+    /// Kind of this error.
+    fn kind(&self) -> SerialErrorKind;
+}
+impl SerialError for ! {
+    fn kind(&self) -> SerialErrorKind {
+        *self
+    }
+}
+impl SerialError for SerialErrorKind {
+    fn kind(&self) -> SerialErrorKind {
+        *self
+    }
+}
+
+/// Error reported by a Dynamixel controller.
+#[derive(Clone, Debug)]
 pub struct DynamixelError {
+    /// Kind of error
     pub error: ErrorType,
 }
 impl DynamixelError {
+    /// The received bytes are not a valid StatusPacket.
     pub fn parsing_error() -> DynamixelError {
         DynamixelError {
             error: ErrorType::Parsing,
         }
     }
+    /// The register is not supported.
     pub fn unsupported_register() -> DynamixelError {
         DynamixelError {
             error: ErrorType::UnsupportedRegister,
         }
     }
+    /// The motor answered with the error code `e`.
     pub fn status_error_code(e: u8) -> DynamixelError {
         DynamixelError {
             error: ErrorType::StatusError(e),
         }
     }
+    /// The motor did not understand the instruction.
+    pub fn instruction_error() -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::InstructionError,
+        }
+    }
+    /// The motor has a hardware error latched.
+    pub fn alert() -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::Alert,
+        }
+    }
+    /// The CRC of the received StatusPacket does not match its content.
     pub fn invalid_checksum() -> DynamixelError {
         DynamixelError {
             error: ErrorType::InvalidChecksum,
         }
     }
-    pub fn timeout() -> DynamixelError {
+    /// The StatusPacket was not received in time, after `received` bytes.
+    pub fn timeout(received: usize) -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::Timeout { received },
+        }
+    }
+    /// The value can not be sent to the motor.
+    pub fn invalid_value() -> DynamixelError {
         DynamixelError {
-            error: ErrorType::Timeout,
+            error: ErrorType::InvalidValue,
         }
     }
+    /// The serial transport reported an error of the given `kind`.
+    pub fn serial(kind: SerialErrorKind) -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::Serial(kind),
+        }
+    }
+    /// The value read back after a write does not match the written value.
+    pub fn verification_failed() -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::VerificationFailed,
+        }
+    }
+    /// The EEPROM register can not be written while the torque is enabled.
+    pub fn torque_must_be_disabled() -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::TorqueMustBeDisabled,
+        }
+    }
+    /// The motor was still moving when the wait for it to stop timed out.
+    pub fn still_moving() -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::StillMoving,
+        }
+    }
+    /// The motor did not reach its goal position in time.
+    pub fn not_in_position() -> DynamixelError {
+        DynamixelError {
+            error: ErrorType::NotInPosition,
+        }
+    }
+    /// Description of the error.
+    ///
+    /// It allocates, use `ErrorType::as_str` or the `Display` rendering on the error paths which must not.
+    #[cfg(feature = "std")]
     pub fn description(&self) -> String {
         format!("Dynxamiel Error: {:?}", self.error)
     }
 }
+impl From<SerialErrorKind> for DynamixelError {
+    fn from(kind: SerialErrorKind) -> DynamixelError {
+        DynamixelError::serial(kind)
+    }
+}
+impl fmt::Display for DynamixelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dynamixel Error: {:?}", self.error)
+    }
+}
+#[cfg(feature = "std")]
+impl ::std::error::Error for DynamixelError {
+    fn description(&self) -> &str {
+        self.error.as_str()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stable_codes() {
+        assert_eq!(ErrorType::Parsing.as_code(), 1);
+        assert_eq!(ErrorType::UnsupportedRegister.as_code(), 2);
+        assert_eq!(ErrorType::Alert.as_code(), 3);
+        assert_eq!(ErrorType::InvalidChecksum.as_code(), 4);
+        assert_eq!(ErrorType::Timeout { received: 0 }.as_code(), 5);
+        assert_eq!(ErrorType::Timeout { received: 7 }.as_code(), 5);
+        assert_eq!(ErrorType::InvalidValue.as_code(), 6);
+        assert_eq!(ErrorType::VerificationFailed.as_code(), 7);
+        assert_eq!(ErrorType::TorqueMustBeDisabled.as_code(), 8);
+        assert_eq!(ErrorType::InstructionError.as_code(), 9);
+        assert_eq!(ErrorType::StillMoving.as_code(), 10);
+        assert_eq!(ErrorType::NotInPosition.as_code(), 11);
+        assert_eq!(ErrorType::StatusError(0x07).as_code(), 0x107);
+        assert_eq!(ErrorType::StatusError(0x87).as_code(), 0x187);
+
+        let serial = |kind| ErrorType::Serial(kind).as_code();
+        assert_eq!(serial(SerialErrorKind::Framing), 0x200);
+        assert_eq!(serial(SerialErrorKind::Noise), 0x201);
+        assert_eq!(serial(SerialErrorKind::Overrun), 0x202);
+        assert_eq!(serial(SerialErrorKind::Parity), 0x203);
+        assert_eq!(serial(SerialErrorKind::Other), 0x204);
+        assert_eq!(serial(SerialErrorKind::TxTimeout), 0x205);
+    }
+}
@@ -22,32 +22,74 @@
 #![feature(const_fn)]
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(not(feature = "std"), feature(alloc))]
+#![cfg_attr(test, feature(test))]
 
 #[cfg(not(feature = "std"))]
-#[macro_use(format, vec)]
+#[macro_use(vec)]
 extern crate alloc;
 
 extern crate embedded_hal as hal;
+use hal::digital::OutputPin;
 use hal::serial::{Read, Write};
 use hal::time::Time;
 
+#[cfg(feature = "crc16")]
 extern crate crc16;
 #[macro_use(block)]
 extern crate nb;
 
 mod error;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub use error::{DynamixelError, ErrorType, SerialError, SerialErrorKind};
 #[macro_use]
 pub mod motors;
 #[macro_use]
 mod protocol;
-pub use protocol::ControllerV2;
+#[cfg(feature = "std")]
+pub mod shared_bus;
+#[cfg(feature = "util")]
+pub mod util;
+pub use protocol::{
+    encode_ping, encode_read_data, encode_sync_write_data, encode_write_data, max_packet_size,
+    MAX_PACKET_SIZE, MAX_REGISTER_PACKET_LENGTH,
+};
+pub use protocol::{
+    Config, Controller, ControllerV2, ControllerV2Builder, Direction, Health, MotorId,
+    NoDirectionPin, PingInfo, Protocol, ResetScope, SingleId, StatusPacket, StatusPacketDecoder,
+    StatusReturnLevel, SyncGroup, V2,
+};
+#[cfg(feature = "std")]
+pub use protocol::{LoggedPacket, PacketLogger};
 
 /// Create a controller for the Dynamixel protocol V2 using a serial RX/TX
 pub fn with_protocol_v2<RX, TX, CLOCK>(rx: RX, tx: TX, clock: CLOCK) -> ControllerV2<RX, TX, CLOCK>
 where
-    RX: Read<u8, Error = !>,
-    TX: Write<u8, Error = !>,
+    RX: Read<u8>,
+    RX::Error: SerialError,
+    TX: Write<u8>,
+    TX::Error: SerialError,
     CLOCK: Time,
 {
     ControllerV2::new(rx, tx, clock)
 }
+
+/// Create a controller for the Dynamixel protocol V2 using a serial RX/TX on a half-duplex RS-485 bus
+///
+/// The driver-enable `de_pin` of the transceiver is driven high while sending and low while receiving.
+pub fn with_protocol_v2_rs485<RX, TX, CLOCK, DE>(
+    rx: RX,
+    tx: TX,
+    clock: CLOCK,
+    de_pin: DE,
+) -> ControllerV2<RX, TX, CLOCK, DE>
+where
+    RX: Read<u8>,
+    RX::Error: SerialError,
+    TX: Write<u8>,
+    TX::Error: SerialError,
+    CLOCK: Time,
+    DE: OutputPin,
+{
+    ControllerV2::with_direction_pin(rx, tx, clock, de_pin)
+}
@@ -0,0 +1,142 @@
+//! In-memory serial transport and clock to test applications without any hardware.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! let serial = MockSerial::new();
+//! let mut c = dynamixel::with_protocol_v2(serial.clone(), serial.clone(), MockClock::new());
+//!
+//! serial.queue_answer(&status_packet_bytes);
+//! let pos = c.read_data(1, &XL_320::PresentPosition).unwrap();
+//! assert_eq!(serial.sent(), expected_instruction_packet_bytes);
+//! ```
+
+extern crate std;
+
+use self::std::cell::{Cell, RefCell};
+use self::std::collections::VecDeque;
+use self::std::rc::Rc;
+use self::std::vec::Vec;
+
+use hal;
+use nb;
+
+/// Loopback serial transport implementing both `Read` and `Write`.
+///
+/// Clones share the same queues, so one clone can be given as RX and another as TX to the controller while the test keeps a third one.
+#[derive(Clone, Default)]
+pub struct MockSerial {
+    answers: Rc<RefCell<VecDeque<u8>>>,
+    sent: Rc<RefCell<Vec<u8>>>,
+    echo: Rc<RefCell<VecDeque<u8>>>,
+    echo_enabled: Rc<Cell<bool>>,
+    flushed: Rc<Cell<bool>>,
+}
+impl MockSerial {
+    /// Create a transport with nothing to read and nothing sent yet.
+    pub fn new() -> MockSerial {
+        MockSerial::default()
+    }
+    /// Queue `bytes` to be read by the controller (e.g. a canned StatusPacket).
+    pub fn queue_answer(&self, bytes: &[u8]) {
+        self.answers.borrow_mut().extend(bytes);
+    }
+    /// Bytes sent by the controller so far.
+    pub fn sent(&self) -> Vec<u8> {
+        self.sent.borrow().clone()
+    }
+    /// Forget the bytes sent so far.
+    pub fn clear_sent(&self) {
+        self.sent.borrow_mut().clear();
+    }
+    /// Number of queued answer bytes not read yet by the controller.
+    pub fn unread(&self) -> usize {
+        self.answers.borrow().len()
+    }
+    /// Read back each sent byte before the queued answers, like a half-duplex bus echoing the transmitted bytes.
+    pub fn set_echo(&self, enabled: bool) {
+        self.echo_enabled.set(enabled);
+    }
+    /// Whether the bytes sent so far were flushed.
+    pub fn flushed(&self) -> bool {
+        self.flushed.get()
+    }
+}
+impl hal::serial::Read<u8> for MockSerial {
+    type Error = !;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let echo = self.echo.borrow_mut().pop_front();
+        echo.or_else(|| self.answers.borrow_mut().pop_front())
+            .ok_or(nb::Error::WouldBlock)
+    }
+}
+impl hal::serial::Write<u8> for MockSerial {
+    type Error = !;
+
+    fn write(&mut self, b: u8) -> nb::Result<(), Self::Error> {
+        self.sent.borrow_mut().push(b);
+        self.flushed.set(false);
+        if self.echo_enabled.get() {
+            self.echo.borrow_mut().push_back(b);
+        }
+        Ok(())
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.flushed.set(true);
+        Ok(())
+    }
+    fn complete(&self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Clock moving forward by 1ms each time it is read, so the controller timeouts elapse without waiting.
+#[derive(Default)]
+pub struct MockClock {
+    t: Cell<u32>,
+}
+impl MockClock {
+    /// Create a clock starting at 0ms.
+    pub fn new() -> MockClock {
+        MockClock::default()
+    }
+    /// Time elapsed in ms, without moving the clock forward.
+    pub fn elapsed(&self) -> u32 {
+        self.t.get()
+    }
+}
+impl hal::time::Time for MockClock {
+    fn now(&self) -> hal::time::MilliSecond {
+        let t = self.t.get();
+        self.t.set(t + 1);
+        hal::time::MilliSecond(t)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use motors::XL_320;
+    use ErrorType;
+
+    #[test]
+    fn read_canned_answer() {
+        let serial = MockSerial::new();
+        let mut c = ::with_protocol_v2(serial.clone(), serial.clone(), MockClock::new());
+
+        serial.queue_answer(&[0xFF, 0xFF, 0xFD, 0x00, 42, 6, 0, 0x55, 0, 0, 23, 4, 242]);
+        assert_eq!(c.read_data(42, &XL_320::PresentSpeed).unwrap(), 23 << 8);
+        assert_eq!(
+            &serial.sent()[..8],
+            &[0xFF, 0xFF, 0xFD, 0x00, 42, 7, 0, 0x02]
+        );
+
+        serial.clear_sent();
+        assert_eq!(
+            c.read_data(42, &XL_320::PresentSpeed).unwrap_err().error,
+            ErrorType::Timeout { received: 0 }
+        );
+        assert!(!serial.sent().is_empty());
+    }
+}
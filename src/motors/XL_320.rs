@@ -1,10 +1,177 @@
 //! Definition of the `XL_320` registers
 
+use super::{HardwareError, Led, LedColor, MotorModel};
+
+/// Maximum raw position
+const MAX_POSITION: u16 = 1023;
+/// The position covers 300° over 0-1023
+const DEGREES_PER_UNIT: f32 = 300.0 / 1023.0;
+/// Maximum raw speed (without the direction bit)
+const MAX_SPEED: u16 = 1023;
+/// Speed unit (in rpm)
+const RPM_PER_UNIT: f32 = 0.111;
+/// Maximum raw load (without the direction bit)
+const MAX_LOAD: u16 = 1023;
+/// Direction bit of the speed and load registers, set when turning clockwise
+const DIRECTION_BIT: u16 = 1 << 10;
+/// Hardware errors matching the bits 0, 1 and 2 of the HardwareErrorStatus and Shutdown registers
+const HARDWARE_ERROR_BITS: [HardwareError; 3] = [
+    HardwareError::OVERLOAD,
+    HardwareError::OVERHEATING,
+    HardwareError::INPUT_VOLTAGE,
+];
+
 register![
-    ID: 0x03, 1,
-    TorqueEnable: 0x18, 1,
-    PresentPosition: 0x25, 2,
-    GoalPosition: 0x1E, 2,
-    MovingSpeed: 0x20, 2,
-    TorqueLimit: 0x23, 2,
+    model: XL320, read_only_end: 0x03, eeprom_end: 0x18;
+    ModelNumber: 0x00, u16,
+    ID: 0x03, u8,
+    BaudRate: 0x04, u8,
+    ReturnDelayTime: 0x05, u8,
+    CWAngleLimit: 0x06, u16,
+    CCWAngleLimit: 0x08, u16,
+    StatusReturnLevel: 0x11, u8,
+    Shutdown: 0x12, u8,
+    TorqueEnable: 0x18, u8,
+    LED: 0x19, u8,
+    PresentPosition: 0x25, u16,
+    PresentSpeed: 0x27, u16,
+    PresentLoad: 0x29, u16,
+    GoalPosition: 0x1E, u16,
+    MovingSpeed: 0x20, u16,
+    TorqueLimit: 0x23, u16,
+    PresentVoltage: 0x2D, u8,
+    PresentTemperature: 0x2E, u8,
+    Moving: 0x31, u8,
+    HardwareErrorStatus: 0x32, u8,
 ];
+
+/// Convert a raw position (0-1023) to an angle in degrees (0-300°).
+///
+/// Positions above 1023 are clamped to 300°.
+pub fn position_to_degrees(raw: i32) -> f32 {
+    super::position_to_degrees(raw, MAX_POSITION, DEGREES_PER_UNIT)
+}
+/// Convert an angle in degrees (0-300°) to a raw position (0-1023).
+///
+/// Angles outside of 0-300° are clamped.
+pub fn degrees_to_position(deg: f32) -> u16 {
+    super::degrees_to_position(deg, MAX_POSITION, DEGREES_PER_UNIT)
+}
+
+/// Convert a raw speed (MovingSpeed or PresentSpeed) to rpm.
+///
+/// The bit 10 gives the direction: counter-clockwise speeds are positive and clockwise speeds are negative.
+///
+/// *Note: In joint mode, a MovingSpeed of 0 means the maximum speed without any speed control.*
+pub fn speed_to_rpm(raw: u16) -> f32 {
+    let rpm = f32::from(raw & MAX_SPEED) * RPM_PER_UNIT;
+
+    if raw & DIRECTION_BIT != 0 {
+        -rpm
+    } else {
+        rpm
+    }
+}
+/// Convert a speed in rpm to a raw MovingSpeed.
+///
+/// Negative speeds (clockwise) set the direction bit, which is only meaningful in wheel mode. Speeds above 113.6 rpm are clamped.
+pub fn rpm_to_speed(rpm: f32) -> u16 {
+    let (rpm, direction) = if rpm < 0.0 {
+        (-rpm, DIRECTION_BIT)
+    } else {
+        (rpm, 0)
+    };
+
+    let raw = rpm / RPM_PER_UNIT + 0.5;
+    let raw = if raw > f32::from(MAX_SPEED) {
+        MAX_SPEED
+    } else {
+        raw as u16
+    };
+
+    raw | direction
+}
+
+/// Decode a raw PresentLoad as a signed percentage of the maximum torque.
+///
+/// The bit 10 gives the direction: counter-clockwise loads are positive and clockwise loads are negative (e.g. 512 is 50% CCW, 1536 is 50% CW).
+pub fn decode_present_load(raw: u16) -> i16 {
+    let magnitude = raw & MAX_LOAD;
+    let percent =
+        ((u32::from(magnitude) * 100 + u32::from(MAX_LOAD) / 2) / u32::from(MAX_LOAD)) as i16;
+
+    if raw & DIRECTION_BIT != 0 {
+        -percent
+    } else {
+        percent
+    }
+}
+
+/// The `XL_320` motor model
+pub struct XL320;
+impl MotorModel for XL320 {
+    const MODEL_NUMBER: u16 = 350;
+    const ID: &'static dyn super::Register = &ID;
+    const BAUD_RATE: &'static dyn super::Register = &BaudRate;
+    const TORQUE_ENABLE: &'static dyn super::Register = &TorqueEnable;
+    const PRESENT_POSITION: &'static dyn super::Register = &PresentPosition;
+    const GOAL_POSITION: &'static dyn super::Register = &GoalPosition;
+    const MOVING: &'static dyn super::Register = &Moving;
+    const PRESENT_TEMPERATURE: &'static dyn super::Register = &PresentTemperature;
+    const PRESENT_VOLTAGE: &'static dyn super::Register = &PresentVoltage;
+    const HARDWARE_ERROR_STATUS: &'static dyn super::Register = &HardwareErrorStatus;
+    const SHUTDOWN: &'static dyn super::Register = &Shutdown;
+    const RETURN_DELAY_TIME: &'static dyn super::Register = &ReturnDelayTime;
+    const RETURN_DELAY_MICROS_PER_UNIT: u16 = 2;
+    const STATUS_RETURN_LEVEL: &'static dyn super::Register = &StatusReturnLevel;
+    const MIN_POSITION_LIMIT: &'static dyn super::Register = &CWAngleLimit;
+    const MAX_POSITION_LIMIT: &'static dyn super::Register = &CCWAngleLimit;
+
+    fn baud_rate_index(baud: super::BaudRate) -> Option<u8> {
+        match baud {
+            super::BaudRate::B9600 => Some(0),
+            super::BaudRate::B57600 => Some(1),
+            super::BaudRate::B115200 => Some(2),
+            super::BaudRate::B1M => Some(3),
+            _ => None,
+        }
+    }
+    fn position_to_degrees(raw: i32) -> f32 {
+        position_to_degrees(raw)
+    }
+    fn degrees_to_position(deg: f32) -> u16 {
+        degrees_to_position(deg)
+    }
+    /// The XL-320 only reports overload (bit 0), overheating (bit 1) and input voltage (bit 2) errors.
+    fn decode_hardware_error(raw: u8) -> HardwareError {
+        HARDWARE_ERROR_BITS
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| raw & (1 << bit) != 0)
+            .fold(HardwareError::default(), |e, (_, &flag)| e | flag)
+    }
+    fn encode_hardware_error(errors: HardwareError) -> u8 {
+        HARDWARE_ERROR_BITS
+            .iter()
+            .enumerate()
+            .filter(|&(_, &flag)| errors.contains(flag))
+            .fold(0, |raw, (bit, _)| raw | 1 << bit)
+    }
+}
+/// The XL-320 has a RGB LED, each bit of the register turning on one color (red, green then blue).
+impl Led for XL320 {
+    const LED: &'static dyn super::Register = &LED;
+
+    fn led_value(color: LedColor) -> Option<u8> {
+        Some(match color {
+            LedColor::Off => 0,
+            LedColor::Red => 1,
+            LedColor::Green => 2,
+            LedColor::Yellow => 3,
+            LedColor::Blue => 4,
+            LedColor::Purple => 5,
+            LedColor::Cyan => 6,
+            LedColor::White => 7,
+        })
+    }
+}
@@ -0,0 +1,175 @@
+//! Definition of the `XM_430` registers
+
+use super::{
+    CurrentSensing, HardwareError, IndirectAddressing, Led, LedColor, MotionStatus, MotorModel,
+    OperatingModes, PositionGains, PwmControl, VelocityControl, VelocityProfile,
+};
+
+/// Maximum raw position
+const MAX_POSITION: u16 = 4095;
+/// One turn is divided in 4096 positions
+const DEGREES_PER_UNIT: f32 = 360.0 / 4096.0;
+/// Current unit (in mA)
+const MILLIAMPS_PER_UNIT: f32 = 2.69;
+/// Maximum raw current (the default CurrentLimit)
+const MAX_CURRENT: u16 = 1193;
+/// Maximum raw PWM (the default PWMLimit, 100%)
+const MAX_PWM: u16 = 885;
+/// Maximum raw velocity (the default VelocityLimit)
+const MAX_VELOCITY: u32 = 167;
+
+register![
+    model: XM430, read_only_end: 0x07, eeprom_end: 0x40;
+    ModelNumber: 0x00, u16,
+    ID: 0x07, u8,
+    BaudRate: 0x08, u8,
+    ReturnDelayTime: 0x09, u8,
+    OperatingMode: 0x0B, u8,
+    MaxPositionLimit: 0x30, u32,
+    MinPositionLimit: 0x34, u32,
+    Shutdown: 0x3F, u8,
+    TorqueEnable: 0x40, u8,
+    LED: 0x41, u8,
+    StatusReturnLevel: 0x44, u8,
+    HardwareErrorStatus: 0x46, u8,
+    PositionDGain: 0x50, u16,
+    PositionIGain: 0x52, u16,
+    PositionPGain: 0x54, u16,
+    Feedforward2ndGain: 0x58, u16,
+    Feedforward1stGain: 0x5A, u16,
+    GoalPWM: 0x64, i16,
+    GoalCurrent: 0x66, i16,
+    GoalVelocity: 0x68, i32,
+    ProfileAcceleration: 0x6C, u32,
+    ProfileVelocity: 0x70, u32,
+    GoalPosition: 0x74, u32,
+    RealtimeTick: 0x78, u16,
+    Moving: 0x7A, u8,
+    MovingStatus: 0x7B, u8,
+    PresentPWM: 0x7C, i16,
+    PresentCurrent: 0x7E, i16,
+    PresentVelocity: 0x80, i32,
+    PresentPosition: 0x84, i32,
+    PresentInputVoltage: 0x90, u16,
+    PresentTemperature: 0x92, u8,
+];
+
+/// Convert a raw position (0-4095) to an angle in degrees (0-360°).
+///
+/// Positions outside of a turn (extended-position mode) are converted as is, e.g. -4096 is -360°.
+pub fn position_to_degrees(raw: i32) -> f32 {
+    raw as f32 * DEGREES_PER_UNIT
+}
+/// Convert an angle in degrees (0-360°) to a raw position (0-4095).
+///
+/// Angles outside of 0-360° are clamped.
+pub fn degrees_to_position(deg: f32) -> u16 {
+    super::degrees_to_position(deg, MAX_POSITION, DEGREES_PER_UNIT)
+}
+
+/// The `XM_430` motor model
+pub struct XM430;
+impl MotorModel for XM430 {
+    const MODEL_NUMBER: u16 = 1020;
+    const ID: &'static dyn super::Register = &ID;
+    const BAUD_RATE: &'static dyn super::Register = &BaudRate;
+    const TORQUE_ENABLE: &'static dyn super::Register = &TorqueEnable;
+    const PRESENT_POSITION: &'static dyn super::Register = &PresentPosition;
+    const GOAL_POSITION: &'static dyn super::Register = &GoalPosition;
+    const MOVING: &'static dyn super::Register = &Moving;
+    const PRESENT_TEMPERATURE: &'static dyn super::Register = &PresentTemperature;
+    const PRESENT_VOLTAGE: &'static dyn super::Register = &PresentInputVoltage;
+    const HARDWARE_ERROR_STATUS: &'static dyn super::Register = &HardwareErrorStatus;
+    const SHUTDOWN: &'static dyn super::Register = &Shutdown;
+    const RETURN_DELAY_TIME: &'static dyn super::Register = &ReturnDelayTime;
+    const RETURN_DELAY_MICROS_PER_UNIT: u16 = 2;
+    const STATUS_RETURN_LEVEL: &'static dyn super::Register = &StatusReturnLevel;
+    const MIN_POSITION_LIMIT: &'static dyn super::Register = &MinPositionLimit;
+    const MAX_POSITION_LIMIT: &'static dyn super::Register = &MaxPositionLimit;
+
+    fn baud_rate_index(baud: super::BaudRate) -> Option<u8> {
+        match baud {
+            super::BaudRate::B9600 => Some(0),
+            super::BaudRate::B57600 => Some(1),
+            super::BaudRate::B115200 => Some(2),
+            super::BaudRate::B1M => Some(3),
+            super::BaudRate::B2M => Some(4),
+            super::BaudRate::B3M => Some(5),
+            super::BaudRate::B4M => Some(6),
+            super::BaudRate::B4_5M => Some(7),
+        }
+    }
+    fn position_to_degrees(raw: i32) -> f32 {
+        position_to_degrees(raw)
+    }
+    fn degrees_to_position(deg: f32) -> u16 {
+        degrees_to_position(deg)
+    }
+    fn decode_hardware_error(raw: u8) -> HardwareError {
+        HardwareError::from_bits_truncate(raw)
+    }
+    fn encode_hardware_error(errors: HardwareError) -> u8 {
+        errors.bits()
+    }
+}
+impl IndirectAddressing for XM430 {
+    const INDIRECT_ADDRESS: u16 = 168;
+    const INDIRECT_DATA: u16 = 224;
+    const INDIRECT_SLOTS: u16 = 20;
+}
+impl CurrentSensing for XM430 {
+    const PRESENT_CURRENT: &'static dyn super::Register = &PresentCurrent;
+    const GOAL_CURRENT: &'static dyn super::Register = &GoalCurrent;
+    const MILLIAMPS_PER_UNIT: f32 = MILLIAMPS_PER_UNIT;
+    const MAX_CURRENT: u16 = MAX_CURRENT;
+}
+impl OperatingModes for XM430 {
+    const OPERATING_MODE: &'static dyn super::Register = &OperatingMode;
+
+    fn supports_operating_mode(_: super::OperatingMode) -> bool {
+        true
+    }
+}
+/// The X-series have a single red LED, which can only be turned on or off.
+impl Led for XM430 {
+    const LED: &'static dyn super::Register = &LED;
+
+    fn led_value(color: LedColor) -> Option<u8> {
+        match color {
+            LedColor::Off => Some(0),
+            LedColor::Red => Some(1),
+            _ => None,
+        }
+    }
+}
+impl VelocityProfile for XM430 {
+    const PROFILE_VELOCITY: &'static dyn super::Register = &ProfileVelocity;
+    const PROFILE_ACCELERATION: &'static dyn super::Register = &ProfileAcceleration;
+}
+/// PresentVelocity (0x80) is right before PresentPosition (0x84), so both can be read at once.
+impl VelocityControl for XM430 {
+    const PRESENT_VELOCITY: &'static dyn super::Register = &PresentVelocity;
+    const GOAL_VELOCITY: &'static dyn super::Register = &GoalVelocity;
+    const MAX_VELOCITY: u32 = MAX_VELOCITY;
+}
+impl PwmControl for XM430 {
+    const GOAL_PWM: &'static dyn super::Register = &GoalPWM;
+    const PRESENT_PWM: &'static dyn super::Register = &PresentPWM;
+    const MAX_PWM: u16 = MAX_PWM;
+}
+impl super::RealtimeTick for XM430 {
+    const REALTIME_TICK: &'static dyn super::Register = &RealtimeTick;
+    const MAX_TICK: u16 = 32767;
+}
+/// The gains are converted to the controller coefficients as KPP = P / 128, KPI = I / 65536, KPD = D / 16, KFF1 = FF1 / 4 and KFF2 = FF2 / 4.
+impl PositionGains for XM430 {
+    const POSITION_P_GAIN: &'static dyn super::Register = &PositionPGain;
+    const POSITION_I_GAIN: &'static dyn super::Register = &PositionIGain;
+    const POSITION_D_GAIN: &'static dyn super::Register = &PositionDGain;
+    const FEEDFORWARD_1ST_GAIN: &'static dyn super::Register = &Feedforward1stGain;
+    const FEEDFORWARD_2ND_GAIN: &'static dyn super::Register = &Feedforward2ndGain;
+    const MAX_GAIN: u16 = 16383;
+}
+impl MotionStatus for XM430 {
+    const MOVING_STATUS: &'static dyn super::Register = &MovingStatus;
+}
@@ -2,34 +2,594 @@
 //!
 //! The supported motors are currently
 //!    * the `XL_320`
+//!    * the `XL_430`
+//!    * the `XM_430`
 //!
-//! Adding support for a new type of motor should only require to add a new submodule with the specific registers.
+//! Adding support for a new type of motor should only require to add a new submodule with the specific registers and its `MotorModel` implementation.
+//!
+//! A `Motor` binds an id to its model so the model specific helpers can be used without giving the model at each call.
 //!
 //! [Contributions are welcomed!](https://github.com/pollen-robotics/rustamixel)
 
+use core::ops;
+
 /// Register trait shared by all dynamixel motor registers.
 pub trait Register {
     /// Address of the register
     fn address(&self) -> u16;
-    /// Length of the register (usually 1 or 2 for the common registers)
+    /// Length of the register (1, 2 or 4 bytes)
     fn length(&self) -> u16;
+    /// Area of the control table where the register is stored
+    fn area(&self) -> RegisterArea {
+        RegisterArea::Ram
+    }
+    /// Whether the register can only be read (e.g. the ModelNumber)
+    fn is_read_only(&self) -> bool {
+        false
+    }
+    /// Model number of the motor model defining the register (`None` for registers not bound to a model)
+    fn model_number(&self) -> Option<u16> {
+        None
+    }
+}
+
+/// Register of the control table of the motor model `M`.
+///
+/// It can be used as a bound to make sure at compile time that a register belongs to the model of a motor.
+pub trait RegisterOf<M: MotorModel>: Register {}
+
+/// Register at an arbitrary address, e.g. a field of a newer firmware not covered by the control tables of this crate.
+///
+/// It is not bound to any model and is assumed to be stored in RAM, so it is never cached.
+///
+/// ## Example
+///
+/// ```ignore
+/// let reg = CustomRegister { addr: 0xA8, len: 2 };
+/// let value = c.read_data(1, &reg)?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CustomRegister {
+    /// Address of the register
+    pub addr: u16,
+    /// Length of the register (1, 2 or 4 bytes)
+    pub len: u16,
+}
+impl Register for CustomRegister {
+    fn address(&self) -> u16 {
+        self.addr
+    }
+    fn length(&self) -> u16 {
+        self.len
+    }
+}
+
+/// Check whether the register `reg` belongs to the control table of the model with the `model_number`.
+///
+/// Registers not bound to a model (e.g. custom registers) are assumed to be supported.
+pub fn model_supports(model_number: u16, reg: &dyn Register) -> bool {
+    match reg.model_number() {
+        Some(n) => n == model_number,
+        None => true,
+    }
+}
+
+/// Area of the control table where a register is stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegisterArea {
+    /// Non-volatile configuration, it can only be written while the torque is disabled.
+    Eeprom,
+    /// Volatile data, reset to its default value at each power-on.
+    Ram,
+}
+
+/// Register whose data type is known at compile time.
+///
+/// The length of the register is given by its data type so it can only be 1, 2 or 4 bytes, and its value is decoded to the right integer type without any runtime length check.
+pub trait TypedRegister: Register {
+    /// Type of the register data (`u8`, `u16`, `i16`, `u32` or `i32`)
+    type Data: RegisterData;
+}
+
+/// Integer types that can be stored in a register.
+pub trait RegisterData: Copy {
+    /// Length of the data in bytes
+    const LENGTH: u16;
+
+    /// Decode the data from its little-endian bytes.
+    fn from_bytes(bytes: &[u8]) -> Self;
+    /// Raw unsigned value of the data (two's complement for signed types).
+    fn to_raw(self) -> u32;
+}
+impl RegisterData for u8 {
+    const LENGTH: u16 = 1;
+
+    fn from_bytes(bytes: &[u8]) -> u8 {
+        bytes[0]
+    }
+    fn to_raw(self) -> u32 {
+        u32::from(self)
+    }
+}
+impl RegisterData for u16 {
+    const LENGTH: u16 = 2;
+
+    fn from_bytes(bytes: &[u8]) -> u16 {
+        u16::from(bytes[1]) << 8 | u16::from(bytes[0])
+    }
+    fn to_raw(self) -> u32 {
+        u32::from(self)
+    }
+}
+impl RegisterData for i16 {
+    const LENGTH: u16 = 2;
+
+    fn from_bytes(bytes: &[u8]) -> i16 {
+        u16::from_bytes(bytes) as i16
+    }
+    fn to_raw(self) -> u32 {
+        u32::from(self as u16)
+    }
+}
+impl RegisterData for u32 {
+    const LENGTH: u16 = 4;
+
+    fn from_bytes(bytes: &[u8]) -> u32 {
+        u32::from(u16::from_bytes(&bytes[2..])) << 16 | u32::from(u16::from_bytes(bytes))
+    }
+    fn to_raw(self) -> u32 {
+        self
+    }
+}
+impl RegisterData for i32 {
+    const LENGTH: u16 = 4;
+
+    fn from_bytes(bytes: &[u8]) -> i32 {
+        u32::from_bytes(bytes) as i32
+    }
+    fn to_raw(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Motor models, identified by their ModelNumber register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    /// `XL_320`
+    XL320,
+    /// `XL_430`
+    XL430,
+    /// `XM_430`
+    XM430,
+    /// Model number of a model not supported by this crate
+    Unknown(u16),
+}
+impl Model {
+    /// Model with the `model_number`, unsupported models are reported as `Unknown`.
+    pub fn from_number(model_number: u16) -> Model {
+        match model_number {
+            n if n == XL_320::XL320::MODEL_NUMBER => Model::XL320,
+            n if n == XL_430::XL430::MODEL_NUMBER => Model::XL430,
+            n if n == XM_430::XM430::MODEL_NUMBER => Model::XM430,
+            n => Model::Unknown(n),
+        }
+    }
+    /// TorqueEnable register of this model, `None` for unknown models.
+    pub fn torque_enable(&self) -> Option<&'static dyn Register> {
+        match *self {
+            Model::XL320 => Some(XL_320::XL320::TORQUE_ENABLE),
+            Model::XL430 => Some(XL_430::XL430::TORQUE_ENABLE),
+            Model::XM430 => Some(XM_430::XM430::TORQUE_ENABLE),
+            Model::Unknown(_) => None,
+        }
+    }
+    /// StatusReturnLevel register of this model, `None` for unknown models.
+    pub fn status_return_level(&self) -> Option<&'static dyn Register> {
+        match *self {
+            Model::XL320 => Some(XL_320::XL320::STATUS_RETURN_LEVEL),
+            Model::XL430 => Some(XL_430::XL430::STATUS_RETURN_LEVEL),
+            Model::XM430 => Some(XM_430::XM430::STATUS_RETURN_LEVEL),
+            Model::Unknown(_) => None,
+        }
+    }
+    /// Name, address and length of all the registers defined for this model, empty for unknown models.
+    pub fn registers(&self) -> &'static [(&'static str, u16, u16)] {
+        match *self {
+            Model::XL320 => XL_320::REGISTERS,
+            Model::XL430 => XL_430::REGISTERS,
+            Model::XM430 => XM_430::REGISTERS,
+            Model::Unknown(_) => &[],
+        }
+    }
+    /// Value of the ModelNumber register of this model.
+    pub fn number(&self) -> u16 {
+        match *self {
+            Model::XL320 => XL_320::XL320::MODEL_NUMBER,
+            Model::XL430 => XL_430::XL430::MODEL_NUMBER,
+            Model::XM430 => XM_430::XM430::MODEL_NUMBER,
+            Model::Unknown(n) => n,
+        }
+    }
+}
+
+/// Model specific characteristics of a motor, implemented by each supported motor.
+pub trait MotorModel {
+    /// Value of the ModelNumber register
+    const MODEL_NUMBER: u16;
+
+    /// ID register
+    const ID: &'static dyn Register;
+    /// BaudRate register
+    const BAUD_RATE: &'static dyn Register;
+    /// TorqueEnable register
+    const TORQUE_ENABLE: &'static dyn Register;
+    /// PresentPosition register
+    const PRESENT_POSITION: &'static dyn Register;
+    /// GoalPosition register
+    const GOAL_POSITION: &'static dyn Register;
+    /// Moving register (1 while the motor is moving)
+    const MOVING: &'static dyn Register;
+    /// PresentTemperature register (in °C)
+    const PRESENT_TEMPERATURE: &'static dyn Register;
+    /// PresentVoltage register (in 0.1V)
+    const PRESENT_VOLTAGE: &'static dyn Register;
+    /// HardwareErrorStatus register
+    const HARDWARE_ERROR_STATUS: &'static dyn Register;
+    /// Shutdown register (hardware errors disabling the torque)
+    const SHUTDOWN: &'static dyn Register;
+    /// ReturnDelayTime register (delay before the StatusPacket)
+    const RETURN_DELAY_TIME: &'static dyn Register;
+    /// Unit of the ReturnDelayTime register (in µs)
+    const RETURN_DELAY_MICROS_PER_UNIT: u16;
+    /// StatusReturnLevel register (which instructions are answered)
+    const STATUS_RETURN_LEVEL: &'static dyn Register;
+    /// Minimum position limit register (MinPositionLimit, or CWAngleLimit for the XL-320)
+    const MIN_POSITION_LIMIT: &'static dyn Register;
+    /// Maximum position limit register (MaxPositionLimit, or CCWAngleLimit for the XL-320)
+    const MAX_POSITION_LIMIT: &'static dyn Register;
+
+    /// Value to write to the BaudRate register to use the `baud` rate (`None` if the model does not support it).
+    fn baud_rate_index(baud: BaudRate) -> Option<u8>;
+    /// Convert a raw position to an angle in degrees.
+    ///
+    /// The position is signed as the X-series PresentPosition is negative below zero in extended-position (multi-turn) mode.
+    fn position_to_degrees(raw: i32) -> f32;
+    /// Convert an angle in degrees to a raw position.
+    fn degrees_to_position(deg: f32) -> u16;
+    /// Decode a raw HardwareErrorStatus, whose bits depend on the model.
+    fn decode_hardware_error(raw: u8) -> HardwareError;
+    /// Encode hardware errors with the bits of the model, the errors it does not support are dropped.
+    fn encode_hardware_error(errors: HardwareError) -> u8;
+}
+
+/// Hardware errors latched by a motor, decoded from its HardwareErrorStatus register.
+///
+/// The flags follow the X-series bit layout, other models are converted to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HardwareError {
+    bits: u8,
+}
+impl HardwareError {
+    /// The input voltage is out of the operating range.
+    pub const INPUT_VOLTAGE: HardwareError = HardwareError { bits: 1 << 0 };
+    /// The internal temperature is above the temperature limit.
+    pub const OVERHEATING: HardwareError = HardwareError { bits: 1 << 2 };
+    /// The motor encoder is not working properly.
+    pub const MOTOR_ENCODER: HardwareError = HardwareError { bits: 1 << 3 };
+    /// An electrical shock or insufficient power was detected.
+    pub const ELECTRICAL_SHOCK: HardwareError = HardwareError { bits: 1 << 4 };
+    /// The motor can not reach its goal with the maximum torque.
+    pub const OVERLOAD: HardwareError = HardwareError { bits: 1 << 5 };
+
+    const ALL: u8 = 0b0011_1101;
+
+    /// Decode raw X-series bits, the undocumented bits are dropped.
+    pub fn from_bits_truncate(bits: u8) -> HardwareError {
+        HardwareError {
+            bits: bits & HardwareError::ALL,
+        }
+    }
+    /// Raw X-series bits.
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+    /// No hardware error is latched.
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+    /// All the flags of `other` are set.
+    pub fn contains(&self, other: HardwareError) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+/// Hardware errors which make a motor disable its torque, written to its Shutdown register.
+///
+/// It uses the same flags as the HardwareErrorStatus, e.g. `HardwareError::OVERHEATING | HardwareError::ELECTRICAL_SHOCK`.
+pub type ShutdownConfig = HardwareError;
+
+impl ops::BitOr for HardwareError {
+    type Output = HardwareError;
+
+    fn bitor(self, other: HardwareError) -> HardwareError {
+        HardwareError {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
+/// Indirect addressing, implemented by the motors supporting it (e.g. the X-series).
+///
+/// Each IndirectAddress register maps one byte of the control table to the matching byte of the IndirectData block,
+/// so non-contiguous registers can be read or written with a single instruction.
+pub trait IndirectAddressing: MotorModel {
+    /// Address of the first IndirectAddress register (2 bytes per slot)
+    const INDIRECT_ADDRESS: u16;
+    /// Address of the first IndirectData register (1 byte per slot)
+    const INDIRECT_DATA: u16;
+    /// Number of indirect slots
+    const INDIRECT_SLOTS: u16;
+}
+
+/// Current sensing, implemented by the motors measuring their current (e.g. the MX and X-series supporting current control).
+pub trait CurrentSensing: MotorModel {
+    /// PresentCurrent register (signed, counter-clockwise currents are positive)
+    const PRESENT_CURRENT: &'static dyn Register;
+    /// GoalCurrent register (signed), used in current control modes
+    const GOAL_CURRENT: &'static dyn Register;
+    /// Current unit of the PresentCurrent and GoalCurrent registers (in mA)
+    const MILLIAMPS_PER_UNIT: f32;
+    /// Maximum raw GoalCurrent magnitude
+    const MAX_CURRENT: u16;
+}
+
+/// Control modes of the X-series, selected with the OperatingMode register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperatingMode {
+    /// Control the current (torque) with GoalCurrent
+    Current = 0,
+    /// Control the velocity with GoalVelocity
+    Velocity = 1,
+    /// Control the position within a single turn with GoalPosition (default)
+    Position = 3,
+    /// Control the position over multiple turns with GoalPosition
+    ExtendedPosition = 4,
+    /// Control the position over multiple turns with GoalPosition, the current being limited by GoalCurrent
+    CurrentBasedPosition = 5,
+    /// Control the PWM (voltage) with GoalPWM
+    Pwm = 16,
+}
+
+/// Selectable operating modes, implemented by the motors supporting them (e.g. the X-series).
+pub trait OperatingModes: MotorModel {
+    /// OperatingMode register
+    const OPERATING_MODE: &'static dyn Register;
+
+    /// The `mode` can be used on this model.
+    fn supports_operating_mode(mode: OperatingMode) -> bool;
+}
+
+/// Velocity profile of the position moves, implemented by the motors supporting it (e.g. the X-series).
+///
+/// The motor generates a trapezoidal velocity profile to reach the goal position, limited by these registers (0 means no limit).
+pub trait VelocityProfile: MotorModel {
+    /// ProfileVelocity register (maximum velocity of the profile)
+    const PROFILE_VELOCITY: &'static dyn Register;
+    /// ProfileAcceleration register (acceleration of the profile)
+    const PROFILE_ACCELERATION: &'static dyn Register;
+}
+
+/// PID and feedforward gains of the position controller, implemented by the motors supporting their tuning (e.g. the X-series).
+///
+/// The gains are raw register values, see the implementation of each model for their units.
+pub trait PositionGains: MotorModel {
+    /// PositionPGain register
+    const POSITION_P_GAIN: &'static dyn Register;
+    /// PositionIGain register
+    const POSITION_I_GAIN: &'static dyn Register;
+    /// PositionDGain register
+    const POSITION_D_GAIN: &'static dyn Register;
+    /// Feedforward1stGain register (velocity feedforward)
+    const FEEDFORWARD_1ST_GAIN: &'static dyn Register;
+    /// Feedforward2ndGain register (acceleration feedforward)
+    const FEEDFORWARD_2ND_GAIN: &'static dyn Register;
+    /// Maximum raw value of the gain registers
+    const MAX_GAIN: u16;
+}
+
+/// Velocity control, implemented by the motors measuring their velocity (e.g. the X-series).
+pub trait VelocityControl: MotorModel {
+    /// PresentVelocity register (signed)
+    const PRESENT_VELOCITY: &'static dyn Register;
+    /// GoalVelocity register (signed), used in velocity control mode
+    const GOAL_VELOCITY: &'static dyn Register;
+    /// Maximum raw GoalVelocity magnitude
+    const MAX_VELOCITY: u32;
+}
+
+/// Colors of the motor LED.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LedColor {
+    /// LED turned off
+    Off,
+    /// Red
+    Red,
+    /// Green
+    Green,
+    /// Yellow (red and green)
+    Yellow,
+    /// Blue
+    Blue,
+    /// Purple (red and blue)
+    Purple,
+    /// Cyan (green and blue)
+    Cyan,
+    /// White (red, green and blue)
+    White,
+}
+
+/// LED control, implemented by the motors having a LED.
+pub trait Led: MotorModel {
+    /// LED register
+    const LED: &'static dyn Register;
+
+    /// Value to write to the LED register to show the `color` (`None` if the LED of the model can not show it).
+    fn led_value(color: LedColor) -> Option<u8>;
+}
+
+/// PWM control, implemented by the motors supporting it (e.g. the X-series).
+pub trait PwmControl: MotorModel {
+    /// GoalPWM register (signed), used in PWM control mode
+    const GOAL_PWM: &'static dyn Register;
+    /// PresentPWM register (signed), the PWM actually applied
+    const PRESENT_PWM: &'static dyn Register;
+    /// Maximum raw GoalPWM magnitude
+    const MAX_PWM: u16;
+}
+
+/// Realtime tick, implemented by the motors counting time (e.g. the X-series).
+pub trait RealtimeTick: MotorModel {
+    /// RealtimeTick register, in ms wrapping after `MAX_TICK`
+    const REALTIME_TICK: &'static dyn Register;
+    /// Maximum value of the RealtimeTick register before it wraps to 0
+    const MAX_TICK: u16;
+}
+
+/// Motion status, implemented by the motors reporting the state of their profile (e.g. the X-series).
+pub trait MotionStatus: MotorModel {
+    /// MovingStatus register
+    const MOVING_STATUS: &'static dyn Register;
+}
+
+/// Motion state of a motor, decoded from its MovingStatus register.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MovingStatus {
+    /// The present position is within the moving threshold of the goal position (bit 0).
+    pub in_position: bool,
+    /// The profile is still generating goal positions (bit 1).
+    pub profile_ongoing: bool,
+}
+impl MovingStatus {
+    /// Decode a raw MovingStatus, the other bits (following error, profile type) are dropped.
+    pub fn from_raw(raw: u8) -> MovingStatus {
+        MovingStatus {
+            in_position: raw & 1 << 0 != 0,
+            profile_ongoing: raw & 1 << 1 != 0,
+        }
+    }
+    /// The motion is over: the profile ended and the motor reached its goal.
+    pub fn is_done(&self) -> bool {
+        self.in_position && !self.profile_ongoing
+    }
+}
+
+/// Convert a raw position to degrees, positions outside of 0 to `max_position` are clamped.
+fn position_to_degrees(raw: i32, max_position: u16, degrees_per_unit: f32) -> f32 {
+    let raw = if raw > i32::from(max_position) {
+        max_position
+    } else if raw > 0 {
+        raw as u16
+    } else {
+        0
+    };
+
+    f32::from(raw) * degrees_per_unit
 }
 
+/// Convert an angle in degrees to a raw position, angles outside of the position range are clamped.
+fn degrees_to_position(deg: f32, max_position: u16, degrees_per_unit: f32) -> u16 {
+    let max_degrees = f32::from(max_position) * degrees_per_unit;
+    let deg = if deg > max_degrees {
+        max_degrees
+    } else if deg > 0.0 {
+        deg
+    } else {
+        0.0
+    };
+
+    (deg / degrees_per_unit + 0.5) as u16
+}
+
+/// Communication speeds supported by the Dynamixel motors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BaudRate {
+    /// 9600 bps
+    B9600,
+    /// 57600 bps
+    B57600,
+    /// 115200 bps
+    B115200,
+    /// 1 Mbps
+    B1M,
+    /// 2 Mbps
+    B2M,
+    /// 3 Mbps
+    B3M,
+    /// 4 Mbps
+    B4M,
+    /// 4.5 Mbps
+    B4_5M,
+}
+impl BaudRate {
+    /// Baud rate in bits per second.
+    pub fn bps(&self) -> u32 {
+        match *self {
+            BaudRate::B9600 => 9_600,
+            BaudRate::B57600 => 57_600,
+            BaudRate::B115200 => 115_200,
+            BaudRate::B1M => 1_000_000,
+            BaudRate::B2M => 2_000_000,
+            BaudRate::B3M => 3_000_000,
+            BaudRate::B4M => 4_000_000,
+            BaudRate::B4_5M => 4_500_000,
+        }
+    }
+}
+
+/// Define the registers of the motor `model`, those below `eeprom_end` are in the EEPROM area and those below `read_only_end` (the model and firmware information) can not be written.
 macro_rules! register {
-    ($($reg:ident : $addr:expr, $len:expr,)+) => {
+    (model: $model:ty, read_only_end: $read_only_end:expr, eeprom_end: $eeprom_end:expr; $($reg:ident : $addr:expr, $data:ty,)+) => {
         $(
             #[allow(missing_docs)]
             pub struct $reg;
             impl super::Register for $reg {
                 fn address(&self) -> u16 { $addr }
-                fn length(&self) -> u16 { $len }
+                fn length(&self) -> u16 { <$data as super::RegisterData>::LENGTH }
+                fn area(&self) -> super::RegisterArea {
+                    if $addr < $eeprom_end {
+                        super::RegisterArea::Eeprom
+                    } else {
+                        super::RegisterArea::Ram
+                    }
+                }
+                fn is_read_only(&self) -> bool {
+                    $addr < $read_only_end
+                }
+                fn model_number(&self) -> Option<u16> {
+                    Some(<$model as super::MotorModel>::MODEL_NUMBER)
+                }
+            }
+            impl super::RegisterOf<$model> for $reg {}
+            impl super::TypedRegister for $reg {
+                type Data = $data;
             }
         )+
+
+        /// Name, address and length of all the registers defined for this model
+        pub const REGISTERS: &[(&str, u16, u16)] = &[
+            $((stringify!($reg), $addr, <$data as super::RegisterData>::LENGTH),)+
+        ];
     }
 }
 
+mod motor;
+pub use self::motor::Motor;
+
 #[allow(non_snake_case)]
 pub mod XL_320;
+#[allow(non_snake_case)]
+pub mod XL_430;
+#[allow(non_snake_case)]
+pub mod XM_430;
 
 macro_rules! pack {
     ($l:expr, $h:expr) => {
@@ -42,25 +602,41 @@ macro_rules! unpack {
     };
 }
 
+/// Write `data` at the beginning of the `buff` slice and return the number of bytes written.
 macro_rules! dxl_code_data {
-    ($len:expr, $data:expr) => {
+    ($len:expr, $data:expr, $buff:expr) => {{
+        let buff: &mut [u8] = $buff;
         match $len {
-            1 => vec![$data as u8],
+            1 => {
+                buff[0] = $data as u8;
+                Ok(1)
+            }
             2 => {
                 let (l, h) = unpack!($data);
-                vec![l, h]
+                buff[..2].copy_from_slice(&[l, h]);
+                Ok(2)
+            }
+            4 => {
+                let (b0, b1) = unpack!($data);
+                let (b2, b3) = unpack!($data >> 16);
+                buff[..4].copy_from_slice(&[b0, b1, b2, b3]);
+                Ok(4)
             }
-            _ => panic!("Unsupported data length"),
+            _ => Err($crate::DynamixelError::unsupported_register()),
         }
-    };
+    }};
 }
 
 macro_rules! dxl_decode_data {
     ($len:expr, $data:expr) => {
         match $len {
-            1 => u16::from($data[0]),
-            2 => pack!($data[0], $data[1]),
-            _ => panic!("Unsupported data length"),
+            1 => Ok(u32::from($data[0])),
+            2 => Ok(u32::from(pack!($data[0], $data[1]))),
+            4 => {
+                Ok(u32::from(pack!($data[2], $data[3])) << 16
+                    | u32::from(pack!($data[0], $data[1])))
+            }
+            _ => Err($crate::DynamixelError::unsupported_register()),
         }
     };
 }
@@ -70,6 +646,188 @@ mod test {
     extern crate rand;
     use self::rand::random;
 
+    use super::{
+        BaudRate, HardwareError, Model, MotorModel, MovingStatus, OperatingMode, OperatingModes,
+        XL_320, XL_320::XL320, XL_430, XL_430::XL430,
+    };
+
+    #[test]
+    fn moving_status() {
+        assert_eq!(MovingStatus::from_raw(0), MovingStatus::default());
+
+        let status = MovingStatus::from_raw(0b0000_0001);
+        assert!(status.in_position && !status.profile_ongoing);
+        assert!(status.is_done());
+
+        let status = MovingStatus::from_raw(0b0000_0010);
+        assert!(!status.in_position && status.profile_ongoing);
+        assert!(!status.is_done());
+
+        // Following error and trapezoidal profile bits are ignored
+        let status = MovingStatus::from_raw(0b0011_1011);
+        assert!(status.in_position && status.profile_ongoing);
+        assert!(!status.is_done());
+    }
+    #[test]
+    fn registers() {
+        assert!(XL_320::REGISTERS.contains(&("ID", 0x03, 1)));
+        assert!(XL_430::REGISTERS.contains(&("PresentPosition", 0x84, 4)));
+        assert_eq!(Model::XL320.registers().len(), XL_320::REGISTERS.len());
+        assert!(Model::Unknown(42).registers().is_empty());
+    }
+    #[test]
+    fn baud_rate_index() {
+        assert_eq!(XL320::baud_rate_index(BaudRate::B1M), Some(3));
+        assert_eq!(XL320::baud_rate_index(BaudRate::B2M), None);
+        assert_eq!(XL430::baud_rate_index(BaudRate::B4M), Some(6));
+    }
+    #[test]
+    fn position_degrees() {
+        assert_eq!(XL_320::position_to_degrees(0), 0.0);
+        assert_eq!(XL_320::position_to_degrees(1023), 300.0);
+        assert_eq!(XL_320::position_to_degrees(2000), 300.0);
+        assert_eq!(XL_320::position_to_degrees(-1), 0.0);
+        assert_eq!(XL_320::degrees_to_position(150.0), 512);
+        assert_eq!(XL_320::degrees_to_position(-10.0), 0);
+        assert_eq!(XL_320::degrees_to_position(400.0), 1023);
+
+        assert_eq!(XL430::position_to_degrees(2048), 180.0);
+        assert_eq!(XL430::position_to_degrees(8192), 720.0);
+        assert_eq!(XL430::position_to_degrees(-4096), -360.0);
+        assert_eq!(XL430::degrees_to_position(90.0), 1024);
+        assert_eq!(XL430::degrees_to_position(360.0), 4095);
+    }
+    #[test]
+    fn speed_rpm() {
+        assert!((XL_320::speed_to_rpm(100) - 11.1).abs() < 1e-3);
+        assert!((XL_320::speed_to_rpm(1024 + 100) + 11.1).abs() < 1e-3);
+        assert_eq!(XL_320::rpm_to_speed(11.1), 100);
+        assert_eq!(XL_320::rpm_to_speed(-11.1), 1024 + 100);
+        assert_eq!(XL_320::rpm_to_speed(500.0), 1023);
+
+        assert!((XL_430::speed_to_rpm(-100) + 22.9).abs() < 1e-3);
+        assert_eq!(XL_430::rpm_to_speed(-22.9), -100);
+    }
+    #[test]
+    fn present_load() {
+        assert_eq!(XL_320::decode_present_load(0), 0);
+        assert_eq!(XL_320::decode_present_load(512), 50);
+        assert_eq!(XL_320::decode_present_load(1023), 100);
+        assert_eq!(XL_320::decode_present_load(1024 + 512), -50);
+
+        assert_eq!(XL_430::decode_present_load(500), 50);
+        assert_eq!(XL_430::decode_present_load(-500_i16 as u16), -50);
+        assert_eq!(XL_430::decode_present_load(-1000_i16 as u16), -100);
+        assert_eq!(XL_430::decode_present_load(0x8000), -3277);
+        assert_eq!(XL_430::decode_present_load(0x7FFF), 3277);
+    }
+    #[test]
+    fn hardware_error() {
+        assert!(XL430::decode_hardware_error(0).is_empty());
+        assert_eq!(
+            XL430::decode_hardware_error(1 << 0),
+            HardwareError::INPUT_VOLTAGE
+        );
+        assert_eq!(
+            XL430::decode_hardware_error(1 << 2),
+            HardwareError::OVERHEATING
+        );
+        assert_eq!(
+            XL430::decode_hardware_error(1 << 3),
+            HardwareError::MOTOR_ENCODER
+        );
+        assert_eq!(
+            XL430::decode_hardware_error(1 << 4),
+            HardwareError::ELECTRICAL_SHOCK
+        );
+        assert_eq!(
+            XL430::decode_hardware_error(1 << 5),
+            HardwareError::OVERLOAD
+        );
+        assert!(XL430::decode_hardware_error(1 << 1 | 1 << 7).is_empty());
+
+        assert_eq!(
+            XL320::decode_hardware_error(1 << 0),
+            HardwareError::OVERLOAD
+        );
+        assert_eq!(
+            XL320::decode_hardware_error(1 << 1),
+            HardwareError::OVERHEATING
+        );
+        assert_eq!(
+            XL320::decode_hardware_error(1 << 2),
+            HardwareError::INPUT_VOLTAGE
+        );
+
+        let all = HardwareError::from_bits_truncate(0xFF);
+        assert_eq!(XL430::encode_hardware_error(all), 0b0011_1101);
+        assert_eq!(XL320::encode_hardware_error(all), 0b0000_0111);
+        assert_eq!(
+            XL320::encode_hardware_error(HardwareError::OVERHEATING),
+            1 << 1
+        );
+
+        let e = XL430::decode_hardware_error(0b0010_0100);
+        assert!(e.contains(HardwareError::OVERHEATING | HardwareError::OVERLOAD));
+        assert!(!e.contains(HardwareError::INPUT_VOLTAGE));
+    }
+    #[test]
+    fn operating_modes() {
+        use super::XM_430::XM430;
+
+        assert!(XL430::supports_operating_mode(OperatingMode::Pwm));
+        assert!(!XL430::supports_operating_mode(OperatingMode::Current));
+        assert!(XM430::supports_operating_mode(OperatingMode::Current));
+    }
+    #[test]
+    fn supported_registers() {
+        use super::{model_supports, XM_430::XM430};
+
+        assert!(model_supports(XL320::MODEL_NUMBER, &XL_320::GoalPosition));
+        assert!(!model_supports(XL430::MODEL_NUMBER, &XL_320::GoalPosition));
+        assert!(!model_supports(XM430::MODEL_NUMBER, &XL_430::GoalPosition));
+        assert!(model_supports(XM430::MODEL_NUMBER, XM430::GOAL_POSITION));
+    }
+    #[test]
+    fn led_value() {
+        use super::{Led, LedColor};
+
+        assert_eq!(XL320::led_value(LedColor::Purple), Some(5));
+        assert_eq!(XL320::led_value(LedColor::White), Some(7));
+        assert_eq!(XL430::led_value(LedColor::Red), Some(1));
+        assert_eq!(XL430::led_value(LedColor::Off), Some(0));
+        assert_eq!(XL430::led_value(LedColor::Blue), None);
+    }
+    #[test]
+    fn model_number() {
+        use super::Model;
+
+        assert_eq!(Model::from_number(350), Model::XL320);
+        assert_eq!(Model::from_number(1060), Model::XL430);
+        assert_eq!(Model::from_number(29), Model::Unknown(29));
+        assert_eq!(Model::XM430.number(), 1020);
+        assert_eq!(Model::Unknown(29).number(), 29);
+    }
+    #[test]
+    fn register_data() {
+        use super::{Register, RegisterArea, RegisterData};
+
+        assert_eq!(XL_320::PresentPosition.length(), 2);
+        assert_eq!(XL_320::BaudRate.area(), RegisterArea::Eeprom);
+        assert_eq!(XL_320::TorqueEnable.area(), RegisterArea::Ram);
+        assert_eq!(XL_430::HardwareErrorStatus.area(), RegisterArea::Ram);
+        assert!(XL_320::ModelNumber.is_read_only());
+        assert!(!XL_320::ID.is_read_only());
+        assert!(!XL_430::ID.is_read_only());
+        assert_eq!(XL_430::PresentVelocity.length(), 4);
+
+        assert_eq!(u16::from_bytes(&[0x10, 0x02]), 0x0210);
+        assert_eq!(i16::from_bytes(&[0x0C, 0xFE]), -500);
+        assert_eq!(u32::from_bytes(&[1, 2, 3, 4]), 0x0403_0201);
+        assert_eq!(i32::from_bytes(&[0x9C, 0xFF, 0xFF, 0xFF]), -100);
+        assert_eq!((-100_i32).to_raw(), 0xFFFF_FF9C);
+        assert_eq!((-500_i16).to_raw(), 0xFE0C);
+    }
     #[test]
     fn unpack2pack() {
         let x: u16 = random();
@@ -85,4 +843,23 @@ mod test {
         assert_eq!(l, ll);
         assert_eq!(h, hh);
     }
+    #[test]
+    fn code2decode() {
+        for &len in &[1, 2, 4] {
+            let x: u32 = random::<u32>() >> (32 - 8 * len);
+            let mut bytes = [0; 4];
+            assert_eq!(dxl_code_data!(len, x, &mut bytes).unwrap(), len as usize);
+            assert_eq!(dxl_decode_data!(len, bytes).unwrap(), x);
+        }
+    }
+    #[test]
+    fn unsupported_length() {
+        use ErrorType;
+
+        let mut bytes = [0; 4];
+        let length: Result<usize, _> = dxl_code_data!(3, 0x0001_0203_u32, &mut bytes);
+        assert_eq!(length.unwrap_err().error, ErrorType::UnsupportedRegister);
+        let data: Result<u32, _> = dxl_decode_data!(3, vec![1_u8, 2, 3]);
+        assert_eq!(data.unwrap_err().error, ErrorType::UnsupportedRegister);
+    }
 }
@@ -0,0 +1,180 @@
+//! Motor bound to its id and model
+
+use core::marker::PhantomData;
+
+use hal;
+
+use error::SerialError;
+use protocol::ControllerV2;
+use DynamixelError;
+
+use super::{HardwareError, MotorModel};
+
+/// A motor identified by its `id` and its model `M`.
+///
+/// The model is carried by the type so the register table and the unit conversions do not have to be given at each call.
+///
+/// ## Example
+///
+/// ```ignore
+/// let shoulder = Motor::<XL430>::new(1);
+///
+/// shoulder.enable_torque(&mut c)?;
+/// shoulder.write_position(&mut c, 90.0)?;
+/// let deg = shoulder.read_position(&mut c)?;
+/// ```
+pub struct Motor<M> {
+    id: u8,
+    model: PhantomData<M>,
+}
+impl<M> Clone for Motor<M> {
+    fn clone(&self) -> Motor<M> {
+        *self
+    }
+}
+impl<M> Copy for Motor<M> {}
+
+impl<M> Motor<M> {
+    /// Bind the motor `id` to the model `M`.
+    pub fn new(id: u8) -> Motor<M> {
+        Motor {
+            id,
+            model: PhantomData,
+        }
+    }
+    /// Id of the motor
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+}
+
+impl<M> Motor<M>
+where
+    M: MotorModel,
+{
+    /// Send a ping to the motor.
+    pub fn ping<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<bool, DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.ping(self.id)
+    }
+    /// Read the present position in degrees.
+    pub fn read_position<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<f32, DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.read_present_position_degrees::<M>(self.id)
+    }
+    /// Set the goal position in degrees, clamped to the position range of the model.
+    pub fn write_position<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+        deg: f32,
+    ) -> Result<(), DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.write_data(
+            self.id,
+            M::GOAL_POSITION,
+            u32::from(M::degrees_to_position(deg)),
+        )
+    }
+    /// Read the present temperature in °C.
+    pub fn read_temperature<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<i16, DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.read_temperature::<M>(self.id)
+    }
+    /// Read the present input voltage in volts.
+    pub fn read_voltage<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<f32, DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.read_voltage::<M>(self.id)
+    }
+    /// Read and decode the latched hardware errors.
+    pub fn read_hardware_error<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<HardwareError, DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.read_hardware_error::<M>(self.id)
+    }
+    /// Enable the torque.
+    pub fn enable_torque<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<(), DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.enable_torque::<M>(self.id)
+    }
+    /// Disable the torque.
+    pub fn disable_torque<RX, TX, CLOCK, DE>(
+        &self,
+        c: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Result<(), DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        c.disable_torque::<M>(self.id)
+    }
+}
@@ -1,17 +1,287 @@
-macro_rules! busy_wait {
-    ($e:expr, $clock:expr, $timeout:expr) => {{
-        let t0 = $clock.now();
-        loop {
-            if let Ok(b) = $e {
-                break Ok(b);
-            }
-
-            if ($clock.now() - t0) > $timeout {
-                break Err(DynamixelError::timeout());
-            }
+#[cfg(not(feature = "std"))]
+use alloc::Vec;
+use core::fmt;
+
+use hal;
+use hal::digital::OutputPin;
+
+use motors::BaudRate;
+use DynamixelError;
+
+/// Placeholder direction pin used when the bus does not need to be switched between transmit and receive (e.g. TTL).
+pub struct NoDirectionPin;
+impl OutputPin for NoDirectionPin {
+    fn set_low(&mut self) {}
+    fn set_high(&mut self) {}
+}
+
+/// Status return level configured on the motors (StatusReturnLevel register).
+///
+/// It defines which instructions are answered with a StatusPacket.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusReturnLevel {
+    /// Only the ping instruction is answered.
+    PingOnly = 0,
+    /// Only the ping and read instructions are answered.
+    PingAndRead = 1,
+    /// All instructions are answered.
+    All = 2,
+}
+impl StatusReturnLevel {
+    /// Level matching the raw value of the StatusReturnLevel register, `None` for an undefined value.
+    pub fn from_raw(raw: u8) -> Option<StatusReturnLevel> {
+        match raw {
+            0 => Some(StatusReturnLevel::PingOnly),
+            1 => Some(StatusReturnLevel::PingAndRead),
+            2 => Some(StatusReturnLevel::All),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration of a controller, without its serial transport.
+///
+/// It is taken from a controller with `config` and applied with `set_config`, e.g. to share the same tuning between the controllers of several buses (possibly on other threads).
+/// The per-motor settings (expected status levels, EEPROM cache content) are not part of it.
+#[derive(Clone)]
+pub struct Config {
+    /// Time allowed for the first byte of a StatusPacket (and for each byte without an inter-byte timeout)
+    pub timeout: hal::time::MilliSecond,
+    /// Time allowed between two bytes once the header has been received
+    pub inter_byte_timeout: Option<hal::time::MilliSecond>,
+    /// Timeout used while scanning, instead of `timeout`
+    pub scan_timeout: Option<hal::time::MilliSecond>,
+    /// Baud rate of the bus, used to extend the timeouts by the transmission time
+    pub baud_rate: Option<BaudRate>,
+    /// Number of times an instruction is sent again after a lost StatusPacket
+    pub retries: u8,
+    /// Also send the instruction again after an invalid checksum
+    pub retry_invalid_checksum: bool,
+    /// Status return level expected by default from the motors
+    pub status_return_level: StatusReturnLevel,
+    /// Record the level read by `read_status_return_level` as the expected status level of the motor
+    pub learn_status_return_level: bool,
+    /// Drop the echo of the sent bytes (single wire half-duplex buses)
+    pub echo_cancellation: bool,
+    /// Discard the bytes left on RX before each transaction
+    pub flush_before_transaction: bool,
+    /// Accept the StatusPackets with only the alert bit set
+    pub ignore_alert: bool,
+    /// Report the writes to the EEPROM refused because of the torque as `ErrorType::TorqueMustBeDisabled`
+    pub eeprom_guard: bool,
+    /// Reject the StatusPackets with an invalid checksum
+    pub crc_check: bool,
+}
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("timeout_ms", &self.timeout.0)
+            .field(
+                "inter_byte_timeout_ms",
+                &self.inter_byte_timeout.map(|t| t.0),
+            )
+            .field("scan_timeout_ms", &self.scan_timeout.map(|t| t.0))
+            .field("baud_rate", &self.baud_rate)
+            .field("retries", &self.retries)
+            .field("retry_invalid_checksum", &self.retry_invalid_checksum)
+            .field("status_return_level", &self.status_return_level)
+            .field("learn_status_return_level", &self.learn_status_return_level)
+            .field("echo_cancellation", &self.echo_cancellation)
+            .field("flush_before_transaction", &self.flush_before_transaction)
+            .field("ignore_alert", &self.ignore_alert)
+            .field("eeprom_guard", &self.eeprom_guard)
+            .field("crc_check", &self.crc_check)
+            .finish()
+    }
+}
+
+/// Direction of a packet given to the packet logger.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// InstructionPacket sent to the motors.
+    Sent,
+    /// StatusPacket received from a motor.
+    Received,
+}
+
+/// Packet given to the packet logger, with its header decoded.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoggedPacket<'a> {
+    /// Whether the packet was sent or received
+    pub direction: Direction,
+    /// Id of the motor the packet was sent to or received from
+    pub id: u8,
+    /// Instruction of the packet (0x55 for a StatusPacket), `None` if the packet is too short to have one
+    pub instruction: Option<u8>,
+    /// Error field of a StatusPacket, `None` for an InstructionPacket or when no error was reported
+    pub error: Option<u8>,
+    /// Raw bytes of the whole packet
+    pub bytes: &'a [u8],
+}
+
+/// Callback given each packet sent or received.
+#[cfg(feature = "std")]
+pub type PacketLogger = Box<dyn FnMut(&LoggedPacket)>;
+
+/// Identification of a motor, answered to a ping.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PingInfo {
+    /// Value of the ModelNumber register
+    pub model_number: u16,
+    /// Version of the motor firmware
+    pub firmware_version: u8,
+}
+
+/// Health metrics of a motor, read at once by `read_health`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    /// Present input voltage in volts
+    pub voltage: f32,
+    /// Present internal temperature in °C
+    pub temperature: i16,
+}
+
+/// Id of a single motor, it can not be the broadcast id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SingleId(u8);
+impl SingleId {
+    /// The id of motor `id`, `None` for the broadcast id.
+    pub fn new(id: u8) -> Option<SingleId> {
+        if id == BROADCAST_ID {
+            None
+        } else {
+            Some(SingleId(id))
+        }
+    }
+    /// Raw value of the id
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+/// Id of the motor(s) an instruction is sent to.
+///
+/// Only a single motor answers an instruction, so the instructions waiting for a StatusPacket (e.g. `read_data`) reject the broadcast id instead of waiting for an answer which never comes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MotorId {
+    /// The motor with this id
+    Single(SingleId),
+    /// All the motors on the bus (id 254)
+    Broadcast,
+}
+impl MotorId {
+    /// Whether all the motors on the bus are addressed.
+    pub fn is_broadcast(self) -> bool {
+        self == MotorId::Broadcast
+    }
+    /// Id of the single motor expected to answer, an `InvalidValue` error for the broadcast id.
+    pub fn single(self) -> Result<u8, DynamixelError> {
+        match self {
+            MotorId::Single(id) => Ok(id.get()),
+            MotorId::Broadcast => Err(DynamixelError::invalid_value()),
+        }
+    }
+}
+impl From<u8> for MotorId {
+    fn from(id: u8) -> MotorId {
+        match SingleId::new(id) {
+            Some(id) => MotorId::Single(id),
+            None => MotorId::Broadcast,
+        }
+    }
+}
+impl From<MotorId> for u8 {
+    fn from(id: MotorId) -> u8 {
+        match id {
+            MotorId::Single(id) => id.get(),
+            MotorId::Broadcast => BROADCAST_ID,
+        }
+    }
+}
+
+/// StatusPacket received from a motor, decoded from the wire format of its protocol (see `Protocol::parse_status`).
+#[derive(Debug)]
+pub struct StatusPacket {
+    id: u8,
+    _length: u16,
+    error_code: Option<u8>,
+    parameters: Vec<u8>,
+}
+impl StatusPacket {
+    /// Id of the motor which sent the packet
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+    /// Error field, `None` when no error was reported
+    pub fn error_code(&self) -> Option<u8> {
+        self.error_code
+    }
+    /// Parameters of the packet (e.g. the data read)
+    pub fn parameters(&self) -> &[u8] {
+        &self.parameters
+    }
+    /// Make sure the packet was sent by motor `id` (any motor may answer a broadcast).
+    fn answer_of(self, id: u8) -> Result<StatusPacket, DynamixelError> {
+        if id != BROADCAST_ID && self.id != id {
+            return Err(DynamixelError::parsing_error());
         }
-    }};
+
+        Ok(self)
+    }
+}
+
+/// What a factory reset keeps from the current configuration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResetScope {
+    /// Reset all the values (the id is reset to 1).
+    All = 0xFF,
+    /// Reset all the values except the id.
+    KeepId = 0x01,
+    /// Reset all the values except the id and the baud rate.
+    KeepIdAndBaudRate = 0x02,
 }
 
+/// Wire format of a version of the Dynamixel protocol: how the packets are framed, checked and parsed.
+///
+/// The `Controller` sends and receives all its packets through this trait, so the methods which do not depend on the wire format (e.g. the retries, `scan` or the unit helpers) are shared by all the protocols.
+/// The instructions specific to a protocol (e.g. the sync read of the protocol v2) are implemented on the controller of this protocol only.
+pub trait Protocol {
+    /// Length of the packet header, up to the length field included
+    const HEADER_LENGTH: usize;
+    /// Length of a StatusPacket without any parameter
+    const MIN_STATUS_LENGTH: usize;
+    /// Number of bytes of the addresses and lengths in the instruction parameters
+    const ADDRESS_LENGTH: usize;
+
+    /// Write into `buff` the whole packet for motor `id` containing `body` ([INST, PARAM 1, ..., PARAM N]) and return its length.
+    ///
+    /// An invalid value error is returned if `buff` is too small.
+    fn frame_into(id: u8, body: &[u8], buff: &mut [u8]) -> Result<usize, DynamixelError>;
+    /// Maximum length of the packet containing a body of `body_length` bytes.
+    fn max_packet_length(body_length: usize) -> usize;
+    /// Whether `bytes` may be the start of a packet header, used to drop the stray bytes before a StatusPacket.
+    fn is_header_start(bytes: &[u8]) -> bool;
+    /// Length of the whole packet starting with `header` (`HEADER_LENGTH` bytes).
+    fn packet_length(header: &[u8]) -> Result<usize, DynamixelError>;
+    /// Id, instruction and error (StatusPacket only) of the whole packet `bytes`, at least `HEADER_LENGTH` bytes long.
+    ///
+    /// The instruction is `None` if the packet is too short to have one, the error is `None` if no error was reported.
+    fn packet_fields(bytes: &[u8]) -> (u8, Option<u8>, Option<u8>);
+    /// Parse a whole StatusPacket, along with whether its checksum is valid (an invalid one is rejected if `check` is set).
+    fn parse_status(bytes: &[u8], check: bool) -> Result<(StatusPacket, bool), DynamixelError>;
+    /// Report the error of a StatusPacket, the alert flag is ignored if `ignore_alert` is set.
+    fn checked(status: StatusPacket, ignore_alert: bool) -> Result<StatusPacket, DynamixelError>;
+}
+
+/// Id addressing all the motors on the bus
+const BROADCAST_ID: u8 = 254;
+
+#[cfg(any(not(feature = "crc16"), test))]
+mod crc;
 mod v2;
-pub use self::v2::ControllerV2;
+pub use self::v2::{
+    encode_ping, encode_read_data, encode_sync_write_data, encode_write_data, max_packet_size,
+    Controller, ControllerV2, ControllerV2Builder, StatusPacketDecoder, SyncGroup, MAX_PACKET_SIZE,
+    MAX_REGISTER_PACKET_LENGTH, V2,
+};
@@ -1,421 +1,4658 @@
 #[cfg(not(feature = "std"))]
-use alloc::Vec;
-use core::ops;
+use alloc::{BTreeMap, Vec};
+use core::marker::PhantomData;
+use core::{cmp, mem, ops};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
 
+#[cfg(feature = "crc16")]
 use crc16;
 use hal;
+use nb;
 
-use error::{DynamixelError, ErrorType};
-use motors::Register;
+use error::{DynamixelError, ErrorType, SerialError, SerialErrorKind};
+use motors::{
+    BaudRate, CurrentSensing, HardwareError, IndirectAddressing, Led, LedColor, Model,
+    MotionStatus, MotorModel, MovingStatus, OperatingMode, OperatingModes, PositionGains,
+    PwmControl, RealtimeTick, Register, RegisterArea, RegisterData, ShutdownConfig, TypedRegister,
+    VelocityControl, VelocityProfile,
+};
+#[cfg(not(feature = "crc16"))]
+use protocol::crc::crc16_buypass;
+use protocol::{
+    Config, Direction, Health, MotorId, NoDirectionPin, PingInfo, Protocol, ResetScope,
+    StatusPacket, StatusReturnLevel, BROADCAST_ID,
+};
+#[cfg(feature = "std")]
+use protocol::{LoggedPacket, PacketLogger};
 
 const TIMEOUT: hal::time::MilliSecond = hal::time::MilliSecond(1);
+const RETRIES: u8 = 0;
+/// Maximum number of unexpected bytes skipped while looking for a StatusPacket header
+const MAX_SKIPPED_BYTES: usize = 32;
+/// The voltage registers are in 0.1V
+const VOLTS_PER_UNIT: f32 = 0.1;
+/// Time given to a motor to switch to its new baud rate before pinging it
+const RESYNC_GRACE_PERIOD: hal::time::MilliSecond = hal::time::MilliSecond(10);
+/// Timeout of the ping confirming the new connection
+const RESYNC_TIMEOUT: hal::time::MilliSecond = hal::time::MilliSecond(100);
+/// Time given to the transmitter to accept a byte (or finish sending) before it is reported as wedged
+const TX_TIMEOUT: hal::time::MilliSecond = hal::time::MilliSecond(100);
+/// Address of the ModelNumber register, the same for all the models
+const MODEL_NUMBER_ADDRESS: u16 = 0x00;
+/// Bit of the StatusPacket error set when the motor has a hardware error latched
+const ALERT_BIT: u8 = 0x80;
+/// StatusPacket error number of a forbidden access (e.g. writing the EEPROM while the torque is enabled)
+const ACCESS_ERROR: u8 = 0x07;
+/// Maximum value of the ReturnDelayTime register
+const MAX_RETURN_DELAY: u8 = 254;
+/// StatusPacket error number of an undefined instruction (or an action without a prior registered write)
+const INSTRUCTION_ERROR: u8 = 0x02;
+/// Time slot of each id in the answers to a broadcast ping, the motors answer one after the other in the order of their ids
+const DISCOVERY_SLOT: hal::time::MilliSecond = hal::time::MilliSecond(3);
 
-/// Dynamixel controller for the protocol v2
-pub struct ControllerV2<RX, TX, CLOCK> {
+/// The Dynamixel protocol 2.0
+///
+/// [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`, INST, PARAM 1, ..., PARAM N, `CRC_L`, `CRC_H`], with byte stuffing and 2 bytes addresses.
+#[derive(Clone, Copy, Debug)]
+pub struct V2;
+impl Protocol for V2 {
+    const HEADER_LENGTH: usize = PacketHeader::length();
+    const MIN_STATUS_LENGTH: usize = MIN_STATUS_PACKET_LENGTH;
+    const ADDRESS_LENGTH: usize = 2;
+
+    fn frame_into(id: u8, body: &[u8], buff: &mut [u8]) -> Result<usize, DynamixelError> {
+        frame_into(id, body, buff)
+    }
+    fn max_packet_length(body_length: usize) -> usize {
+        max_packet_size(body_length.saturating_sub(1))
+    }
+    fn is_header_start(bytes: &[u8]) -> bool {
+        HEADER[..SYNC_LENGTH].starts_with(&bytes[..cmp::min(bytes.len(), SYNC_LENGTH)])
+    }
+    fn packet_length(header: &[u8]) -> Result<usize, DynamixelError> {
+        let header = PacketHeader::from_bytes(header)?;
+        Ok(PacketHeader::length() + header.length as usize)
+    }
+    fn packet_fields(bytes: &[u8]) -> (u8, Option<u8>, Option<u8>) {
+        // The instruction and the error come first in the body, they are never stuffed.
+        let instruction = bytes.get(PacketHeader::length()).cloned();
+        let error = match instruction {
+            Some(STATUS_INSTRUCTION) => bytes
+                .get(PacketHeader::length() + 1)
+                .cloned()
+                .filter(|&e| e != 0),
+            _ => None,
+        };
+
+        (bytes[4], instruction, error)
+    }
+    fn parse_status(bytes: &[u8], check: bool) -> Result<(StatusPacket, bool), DynamixelError> {
+        StatusPacket::parse(bytes, check)
+    }
+    fn checked(status: StatusPacket, ignore_alert: bool) -> Result<StatusPacket, DynamixelError> {
+        status.checked(ignore_alert)
+    }
+}
+
+/// Dynamixel controller, generic over the `Protocol` used on the bus
+pub struct Controller<P, RX, TX, CLOCK, DE = NoDirectionPin> {
+    protocol: PhantomData<P>,
     rx: RX,
     tx: TX,
+    de: Option<DE>,
 
     clock: CLOCK,
     timeout: hal::time::MilliSecond,
+    inter_byte_timeout: Option<hal::time::MilliSecond>,
+    scan_timeout: Option<hal::time::MilliSecond>,
+    baud_rate: Option<BaudRate>,
+    retries: u8,
+    retry_invalid_checksum: bool,
+    status_return_level: StatusReturnLevel,
+    status_return_levels: BTreeMap<u8, StatusReturnLevel>,
+
+    echo_cancellation: bool,
+    flush_before_transaction: bool,
+    pending_echo: usize,
+    ignore_alert: bool,
+    eeprom_guard: bool,
+    /// Values of the EEPROM registers read, by motor id, address and length (`None` when the cache is disabled)
+    eeprom_cache: Option<BTreeMap<(u8, u16, u16), u32>>,
+    /// Record the status return level read from each motor as its expected level
+    learn_status_return_level: bool,
+
+    crc_check: bool,
+    crc_mismatch: bool,
+
+    timing_capture: bool,
+    sent_at: Option<hal::time::MilliSecond>,
+    last_transaction_duration: Option<hal::time::MilliSecond>,
+
+    reception: Option<Reception<P>>,
+    pending_read: Option<(u8, u16, u16)>,
+
+    /// Reused to build SyncWrite packets without allocating at each call
+    body_buffer: Vec<u8>,
+    packet_buffer: Vec<u8>,
+
+    #[cfg(feature = "std")]
+    packet_logger: Option<PacketLogger>,
 }
+/// Dynamixel controller for the protocol v2
+pub type ControllerV2<RX, TX, CLOCK, DE = NoDirectionPin> = Controller<V2, RX, TX, CLOCK, DE>;
 
 impl<RX, TX, CLOCK> ControllerV2<RX, TX, CLOCK>
 where
-    TX: hal::serial::Write<u8, Error = !>,
-    RX: hal::serial::Read<u8, Error = !>,
+    TX: hal::serial::Write<u8>,
+    TX::Error: SerialError,
+    RX: hal::serial::Read<u8>,
+    RX::Error: SerialError,
     CLOCK: hal::time::Time,
 {
     /// Create a new controller for the protocol v2.
     pub fn new(rx: RX, tx: TX, clock: CLOCK) -> ControllerV2<RX, TX, CLOCK> {
-        ControllerV2 {
-            rx,
-            tx,
-            clock,
-            timeout: TIMEOUT,
+        ControllerV2Builder::new(rx, tx, clock).build()
+    }
+}
+
+impl<P, RX, TX, CLOCK, DE> Controller<P, RX, TX, CLOCK, DE>
+where
+    P: Protocol,
+    TX: hal::serial::Write<u8>,
+    TX::Error: SerialError,
+    RX: hal::serial::Read<u8>,
+    RX::Error: SerialError,
+    CLOCK: hal::time::Time,
+    DE: hal::digital::OutputPin,
+{
+    /// Consume the controller and give back its serial RX/TX and clock, e.g. to reconfigure them or drop them in a defined order.
+    ///
+    /// The driver-enable pin, if any, is dropped.
+    pub fn into_parts(self) -> (RX, TX, CLOCK) {
+        (self.rx, self.tx, self.clock)
+    }
+    /// Replace the serial RX/TX (e.g. reconfigured at a new baud rate) while keeping the configuration of the controller, and return the previous ones.
+    ///
+    /// Any partially received StatusPacket or pending echo is discarded.
+    pub fn replace_serial(&mut self, rx: RX, tx: TX) -> (RX, TX) {
+        self.reception = None;
+        self.pending_read = None;
+        self.pending_echo = 0;
+
+        (
+            mem::replace(&mut self.rx, rx),
+            mem::replace(&mut self.tx, tx),
+        )
+    }
+    /// Snapshot of the configuration of the controller (timeouts, retries, baud rate, ...), see `set_config`.
+    pub fn config(&self) -> Config {
+        Config {
+            timeout: self.timeout,
+            inter_byte_timeout: self.inter_byte_timeout,
+            scan_timeout: self.scan_timeout,
+            baud_rate: self.baud_rate,
+            retries: self.retries,
+            retry_invalid_checksum: self.retry_invalid_checksum,
+            status_return_level: self.status_return_level,
+            learn_status_return_level: self.learn_status_return_level,
+            echo_cancellation: self.echo_cancellation,
+            flush_before_transaction: self.flush_before_transaction,
+            ignore_alert: self.ignore_alert,
+            eeprom_guard: self.eeprom_guard,
+            crc_check: self.crc_check,
         }
     }
-    /// Send a ping signal to the specified motor
-    pub fn ping(&mut self, id: u8) -> Result<bool, DynamixelError> {
-        self.send(&InstructionPacket::ping(id));
-
-        match self.recv() {
-            Ok(_) => Ok(true),
-            Err(e) => {
-                if e.error == ErrorType::Timeout {
-                    Ok(false)
-                } else {
-                    Err(e)
+    /// Apply the `config` taken from this or another controller.
+    ///
+    /// The per-motor settings (expected status levels, EEPROM cache) and the transaction in progress are kept.
+    pub fn set_config(&mut self, config: Config) {
+        self.timeout = config.timeout;
+        self.inter_byte_timeout = config.inter_byte_timeout;
+        self.scan_timeout = config.scan_timeout;
+        self.baud_rate = config.baud_rate;
+        self.retries = config.retries;
+        self.retry_invalid_checksum = config.retry_invalid_checksum;
+        self.status_return_level = config.status_return_level;
+        self.learn_status_return_level = config.learn_status_return_level;
+        self.echo_cancellation = config.echo_cancellation;
+        self.flush_before_transaction = config.flush_before_transaction;
+        self.ignore_alert = config.ignore_alert;
+        self.eeprom_guard = config.eeprom_guard;
+        self.crc_check = config.crc_check;
+    }
+    /// Send a custom `instruction` with raw `params` to motor `id` and return the raw parameters of its StatusPacket.
+    ///
+    /// This is an escape hatch for instructions not directly supported by the controller. The packet header, byte stuffing and CRC are handled.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as response. Instructions that are not answered will time out, and for the broadcast id the parameters of the first StatusPacket received are returned (e.g. for a broadcast ping).*
+    pub fn send_instruction<ID>(
+        &mut self,
+        id: ID,
+        instruction: u8,
+        params: &[u8],
+    ) -> Result<Vec<u8>, DynamixelError>
+    where
+        ID: Into<MotorId>,
+    {
+        let id = u8::from(id.into());
+        let packet = InstructionPacket::custom(id, instruction, params.to_vec());
+        self.invalidate_cache(id);
+
+        Ok(self.request(&packet)?.parameters)
+    }
+    /// Discard all the bytes immediately available on RX and return how many were discarded.
+    ///
+    /// This removes the leftovers of a previous transaction (e.g. the end of a StatusPacket received after its timeout) which would otherwise precede the next StatusPacket.
+    pub fn flush_input(&mut self) -> usize {
+        let mut discarded = 0;
+
+        while self.rx.read().is_ok() {
+            discarded += 1;
+        }
+        self.pending_echo = 0;
+        self.reception = None;
+
+        discarded
+    }
+    /// Enable or disable the flush of RX before each transaction (disabled by default), see `flush_input`.
+    pub fn set_flush_before_transaction(&mut self, enabled: bool) {
+        self.flush_before_transaction = enabled;
+    }
+    /// Enable or disable the echo cancellation.
+    ///
+    /// On single-wire half-duplex buses every transmitted byte is echoed back on RX.
+    /// When enabled, the controller discards as many bytes as it sent before looking for the StatusPacket.
+    pub fn set_echo_cancellation(&mut self, enabled: bool) {
+        self.echo_cancellation = enabled;
+    }
+    /// Set the status return level configured on motor `id`.
+    ///
+    /// It overrides the controller status return level for this motor, so the controller does not wait for StatusPackets the motor will never send.
+    pub fn set_expected_status_level(&mut self, id: u8, level: StatusReturnLevel) {
+        self.status_return_levels.insert(id, level);
+    }
+    /// Record the level read by `read_status_return_level` as the expected status level of the motor (disabled by default).
+    ///
+    /// Reading the level of each motor once at startup then avoids waiting for acknowledgments that will never come, without configuring them by hand.
+    pub fn set_status_return_level_learning(&mut self, enabled: bool) {
+        self.learn_status_return_level = enabled;
+    }
+    /// Accept the StatusPackets with only the alert bit set.
+    ///
+    /// By default, an alert (hardware error latched on the motor) is reported as `ErrorType::Alert` even if the instruction was processed.
+    /// When ignored, the instruction result is returned as usual and the hardware error must be checked separately.
+    pub fn set_ignore_alert(&mut self, ignored: bool) {
+        self.ignore_alert = ignored;
+    }
+    /// Enable or disable the CRC check of the StatusPackets (enabled by default).
+    ///
+    /// **Warning:** This is a debugging aid for flaky buses, it must not be disabled in production as corrupted data would be used as is.
+    /// When disabled, StatusPackets with an invalid CRC are parsed anyway so their content can be inspected, and `crc_mismatch` tells whether the CRC of the last one was invalid.
+    pub fn set_crc_check(&mut self, enabled: bool) {
+        self.crc_check = enabled;
+    }
+    /// The CRC of the last StatusPacket received did not match its content (only reported while the CRC check is disabled).
+    pub fn crc_mismatch(&self) -> bool {
+        self.crc_mismatch
+    }
+    /// Enable or disable the timing capture (disabled by default).
+    ///
+    /// When enabled, the controller records the time between sending an InstructionPacket and receiving its (last) StatusPacket, see `last_transaction_duration`.
+    pub fn set_timing_capture(&mut self, enabled: bool) {
+        self.timing_capture = enabled;
+        self.sent_at = None;
+        self.last_transaction_duration = None;
+    }
+    /// Time between sending the last InstructionPacket and successfully receiving its StatusPacket.
+    ///
+    /// For sync reads, it covers all the answers received. It is `None` if the timing capture is disabled or no transaction succeeded yet.
+    /// This is meant to profile a control loop, e.g. to find which motors or registers are the bottleneck.
+    pub fn last_transaction_duration(&self) -> Option<hal::time::MilliSecond> {
+        self.last_transaction_duration
+    }
+    /// Enable or disable the EEPROM guard (enabled by default).
+    ///
+    /// The motors refuse to write their EEPROM registers while the torque is enabled. When enabled, this access error is reported as `ErrorType::TorqueMustBeDisabled` instead of the raw status error.
+    pub fn set_eeprom_guard(&mut self, enabled: bool) {
+        self.eeprom_guard = enabled;
+    }
+    /// Enable or disable the cache of the EEPROM registers (disabled by default).
+    ///
+    /// When enabled, `read_data` only reads an EEPROM register (e.g. id, baud rate, limits) from the motor the first time and then answers from the cache. RAM registers (e.g. position, temperature) are always read from the motor.
+    /// The cache of a motor is invalidated by any instruction sent to it other than a read, see also `invalidate_cache`.
+    pub fn set_eeprom_cache(&mut self, enabled: bool) {
+        self.eeprom_cache = if enabled { Some(BTreeMap::new()) } else { None };
+    }
+    /// Forget the cached EEPROM values of motor `id` (all the motors for the broadcast id).
+    ///
+    /// This is needed when the motor was changed without the controller knowing (e.g. by another controller or power cycled with a different configuration).
+    pub fn invalidate_cache(&mut self, id: u8) {
+        if let Some(ref mut cache) = self.eeprom_cache {
+            if id == BROADCAST_ID {
+                cache.clear();
+            } else {
+                let keys: Vec<(u8, u16, u16)> =
+                    cache.keys().filter(|k| k.0 == id).cloned().collect();
+                for k in keys {
+                    cache.remove(&k);
                 }
             }
         }
     }
-    /// Scan a range of motors id
-    pub fn scan(&mut self, id_range: ops::Range<u8>) -> Result<Vec<u8>, DynamixelError> {
-        let mut v = Vec::new();
+    /// Set a logger called with the raw bytes of each packet sent or received, e.g. to print them as hex when debugging the communication.
+    ///
+    /// Received packets are logged before being checked so corrupted packets (e.g. invalid CRC) are visible too.
+    #[cfg(feature = "std")]
+    pub fn set_packet_logger(&mut self, logger: PacketLogger) {
+        self.packet_logger = Some(logger);
+    }
+    /// Send a ping signal to the specified motor
+    ///
+    /// The broadcast id is rejected with an `InvalidValue` error, see `discover` to ping all the motors.
+    pub fn ping<ID>(&mut self, id: ID) -> Result<bool, DynamixelError>
+    where
+        ID: Into<MotorId>,
+    {
+        let id = id.into().single()?;
 
-        for id in id_range {
-            if self.ping(id)? {
-                v.push(id);
-            }
-        }
+        Ok(self.ping_status(id)?.is_some())
+    }
+    /// Read the ModelNumber register of motor `id` and look up its model.
+    ///
+    /// Models not supported by this crate are reported as `Model::Unknown` with their model number.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_model(&mut self, id: u8) -> Result<Model, DynamixelError> {
+        let bytes = self.read_bytes(id, MODEL_NUMBER_ADDRESS, 2)?;
 
-        Ok(v)
+        Ok(Model::from_number(pack!(bytes[0], bytes[1])))
     }
-    /// Read data from a specified register `REG` on motor `id`.
+    /// Read the value of all the registers defined for the `model` of motor `id`, e.g. to show its full state in a tool.
+    ///
+    /// The values are returned raw with the name of their register, in the order of the register definitions. Printing them is left to the caller.
+    /// The whole control table span is read at once, an unsupported register error is returned for unknown models.
     ///
     /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
-    pub fn read_data<REG>(&mut self, id: u8, reg: &REG) -> Result<u16, DynamixelError>
-    where
-        REG: Register,
-    {
-        let packet = InstructionPacket::read_data(id, reg.address(), reg.length());
+    pub fn dump_registers(
+        &mut self,
+        id: u8,
+        model: Model,
+    ) -> Result<Vec<(&'static str, u32)>, DynamixelError> {
+        let registers = model.registers();
+        let start = registers
+            .iter()
+            .map(|&(_, addr, _)| addr)
+            .min()
+            .ok_or_else(DynamixelError::unsupported_register)?;
+        let end = registers
+            .iter()
+            .map(|&(_, addr, len)| addr + len)
+            .max()
+            .unwrap_or(start);
 
-        self.send(&packet);
-        let status = self.recv()?;
+        let bytes = self.read_bytes(id, start, end - start)?;
+        registers
+            .iter()
+            .map(|&(name, addr, len)| {
+                let offset = (addr - start) as usize;
+                Ok((name, dxl_decode_data!(len, bytes[offset..])?))
+            })
+            .collect()
+    }
+    /// Read the StatusReturnLevel register of motor `id`, found from its model.
+    ///
+    /// When the learning is enabled (see `set_status_return_level_learning`), a valid level is recorded as the expected status level of the motor.
+    /// An unsupported register error is returned for unknown models. A motor answering only pings (level 0) does not answer the read either, so it times out.
+    ///
+    /// *Note: This will send two InstructionPackets to the motor (model and level) and block until the StatusPackets are received as reponse.*
+    pub fn read_status_return_level(&mut self, id: u8) -> Result<u8, DynamixelError> {
+        let reg = self
+            .read_model(id)?
+            .status_return_level()
+            .ok_or_else(DynamixelError::unsupported_register)?;
+        let raw = self.read_data(id, reg)? as u8;
 
-        if (status.parameters.len()) != reg.length() as usize {
-            return Err(DynamixelError::parsing_error());
+        if self.learn_status_return_level {
+            if let Some(level) = StatusReturnLevel::from_raw(raw) {
+                self.set_expected_status_level(id, level);
+            }
         }
 
-        Ok(dxl_decode_data!(reg.length(), status.parameters))
+        Ok(raw)
     }
-    /// Sync read data from a specified register `REG` on a list of motor `id`.
+    /// Scan a range of motors id
+    pub fn scan(&mut self, id_range: ops::Range<u8>) -> Result<Vec<u8>, DynamixelError> {
+        let mut v = Vec::new();
+
+        self.scan_with(id_range, |id| v.push(id))?;
+
+        Ok(v)
+    }
+    /// Scan a range of motors id, calling `found` with each id as soon as it answers.
     ///
-    /// *Note: This will send an InstructionPacket to all targeted motors and block until all the StatusPackets are received as reponse.*
-    pub fn sync_read_data<REG>(&mut self, ids: &[u8], reg: &REG) -> Vec<(u8, u16)>
+    /// Contrary to `scan`, the found motors can be used (e.g. displayed) before the whole range is pinged.
+    /// Both use the scan timeout of the controller if it was set (see `ControllerV2Builder::scan_timeout`).
+    pub fn scan_with<F>(
+        &mut self,
+        id_range: ops::Range<u8>,
+        mut found: F,
+    ) -> Result<(), DynamixelError>
     where
-        REG: Register,
+        F: FnMut(u8),
     {
-        let packet = InstructionPacket::sync_read_data(ids, reg.address(), reg.length());
-        self.send(&packet);
-
-        let mut answer = Vec::new();
+        let timeout = self.timeout;
+        if let Some(scan_timeout) = self.scan_timeout {
+            self.timeout = scan_timeout;
+        }
 
-        for &id in ids {
-            if let Ok(status_packet) = self.recv() {
-                answer.push((id, dxl_decode_data!(reg.length(), status_packet.parameters)));
+        let mut result = Ok(());
+        for id in id_range {
+            match self.ping(id) {
+                Ok(true) => found(id),
+                Ok(false) => {}
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
             }
         }
 
-        answer
+        self.timeout = timeout;
+        result
     }
-    /// Write `data` to a specified register `REG` on motor `id`.
+    /// Read data from a specified register `REG` on motor `id`.
     ///
-    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
-    pub fn write_data<REG>(&mut self, id: u8, reg: &REG, data: u16) -> Result<(), DynamixelError>
+    /// No motor answers a read sent to the broadcast id, so it is rejected with an `InvalidValue` error.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_data<ID, REG>(&mut self, id: ID, reg: &REG) -> Result<u32, DynamixelError>
     where
-        REG: Register,
+        ID: Into<MotorId>,
+        REG: Register + ?Sized,
     {
-        let packet = InstructionPacket::write_data(id, reg.address(), reg.length(), data);
+        let id = id.into().single()?;
+        let cached = reg.area() == RegisterArea::Eeprom;
+        if cached {
+            if let Some(&data) = self
+                .eeprom_cache
+                .as_ref()
+                .and_then(|cache| cache.get(&(id, reg.address(), reg.length())))
+            {
+                return Ok(data);
+            }
+        }
 
-        self.send(&packet);
-        self.recv()?;
+        let data = self.retried(|c| block!(c.read_data_nb(id, reg)))?;
+        if let (true, Some(cache)) = (cached, self.eeprom_cache.as_mut()) {
+            cache.insert((id, reg.address(), reg.length()), data);
+        }
 
-        Ok(())
+        Ok(data)
     }
-    /// Sync write `data` to a specified register `REG` on a list of motor `ids`.
+    /// Read data from a register `REG` whose type is known at compile time on motor `id`.
     ///
-    /// *Note: The motors will not answer after a SyncWrite. `sync_write_data` only blocks during the sending.*
-    pub fn sync_write_data<REG>(&mut self, reg: &REG, data: &[(u8, u16)])
+    /// Contrary to `read_data`, the value is returned with the register data type (e.g. `i16` for signed registers) and its length is checked by the compiler.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_typed<ID, REG>(&mut self, id: ID, reg: &REG) -> Result<REG::Data, DynamixelError>
     where
-        REG: Register,
+        ID: Into<MotorId>,
+        REG: TypedRegister,
     {
-        let packet = InstructionPacket::sync_write_data(reg.address(), reg.length(), data);
+        let bytes = self.read_bytes(id, reg.address(), REG::Data::LENGTH)?;
 
-        self.send(&packet);
+        Ok(REG::Data::from_bytes(&bytes))
     }
+    /// Non-blocking read of data from a specified register `REG` on motor `id`.
+    ///
+    /// The first call sends the InstructionPacket, the following calls return `WouldBlock` until the whole StatusPacket has been received.
+    /// It is meant to be polled (e.g. from a cooperative scheduler) instead of blocking until the answer arrives.
+    pub fn read_data_nb<ID, REG>(&mut self, id: ID, reg: &REG) -> nb::Result<u32, DynamixelError>
+    where
+        ID: Into<MotorId>,
+        REG: Register + ?Sized,
+    {
+        let id = id.into().single()?;
+        let request = (id, reg.address(), reg.length());
 
-    fn send(&mut self, packet: &InstructionPacket) {
-        for b in packet.as_bytes() {
-            block!(self.tx.write(b)).ok();
-        }
-    }
-    fn recv(&mut self) -> Result<StatusPacket, DynamixelError> {
-        let mut bytes = Vec::new();
-        for _ in 0..PacketHeader::length() {
-            bytes.push(busy_wait!(self.rx.read(), self.clock, self.timeout)?);
-        }
-        let header = PacketHeader::from_bytes(&bytes)?;
+        if self.pending_read != Some(request) {
+            let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+            let length = encode_read::<P>(id, reg.address(), reg.length(), &mut buff)?;
 
-        for _ in 0..header.length {
-            bytes.push(busy_wait!(self.rx.read(), self.clock, self.timeout)?);
+            self.send_bytes(&buff[..length])?;
+            self.pending_read = Some(request);
         }
 
-        let p = StatusPacket::from_bytes(&bytes)?;
+        let status = match self.poll_packet() {
+            Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+            status => {
+                self.pending_read = None;
+                status?.answer_of(id)?.checked(self.ignore_alert)?
+            }
+        };
 
-        if let Some(e) = p.error_code {
-            return Err(DynamixelError::status_error_code(e));
+        if (status.parameters.len()) != reg.length() as usize {
+            return Err(nb::Error::Other(DynamixelError::parsing_error()));
         }
 
-        Ok(p)
+        Ok(dxl_decode_data!(reg.length(), status.parameters)?)
     }
-}
-
-#[derive(Clone, Copy, Debug)]
-enum Instruction {
-    Ping = 0x01,
-    ReadData = 0x02,
-    WriteData = 0x03,
-    _Reset = 0x06,
-    SyncRead = 0x82,
-    SyncWrite = 0x83,
-}
-
-const BROADCAST_ID: u8 = 254;
-
-/// Packet header are constructed as follows [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`]
-#[derive(Debug)]
-struct PacketHeader {
-    _id: u8,
-    length: u16,
-}
-impl PacketHeader {
-    fn from_bytes(bytes: &[u8]) -> Result<PacketHeader, DynamixelError> {
-        const HEADER: [u8; 4] = [0xFF, 0xFF, 0xFD, 0x00];
+    /// Read `len` raw bytes starting at address `addr` on motor `id`.
+    ///
+    /// Contrary to `read_data`, the bytes are returned without any decoding so any length can be read (e.g. several adjacent registers or an indirect data block).
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse. The broadcast id is rejected with an `InvalidValue` error.*
+    pub fn read_bytes<ID>(&mut self, id: ID, addr: u16, len: u16) -> Result<Vec<u8>, DynamixelError>
+    where
+        ID: Into<MotorId>,
+    {
+        let id = id.into().single()?;
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = encode_read::<P>(id, addr, len, &mut buff)?;
 
-        assert_eq!(bytes.len(), PacketHeader::length());
+        let status = self.request_bytes(id, &buff[..length])?;
 
-        if bytes[..4] != HEADER {
+        if status.parameters.len() != len as usize {
             return Err(DynamixelError::parsing_error());
         }
 
-        Ok(PacketHeader {
-            _id: bytes[4],
-            length: pack!(bytes[5], bytes[6]),
-        })
+        Ok(status.parameters)
     }
-    const fn length() -> usize {
-        7
+    /// Read `len` raw bytes starting at the register `REG` on motor `id`, e.g. to read several consecutive registers at once.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_block<ID, REG>(
+        &mut self,
+        id: ID,
+        start: &REG,
+        len: u16,
+    ) -> Result<Vec<u8>, DynamixelError>
+    where
+        ID: Into<MotorId>,
+        REG: Register + ?Sized,
+    {
+        self.read_bytes(id, start.address(), len)
     }
-}
+    /// Read the registers `regs` of motor `id` in a single transaction and return their values in the same order.
+    ///
+    /// The whole span from the lowest to the highest register is read, so it is only worth it for registers close to each other (e.g. PresentPosition, PresentSpeed and PresentLoad).
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_registers<ID>(
+        &mut self,
+        id: ID,
+        regs: &[&dyn Register],
+    ) -> Result<Vec<u32>, DynamixelError>
+    where
+        ID: Into<MotorId>,
+    {
+        let start = match regs.iter().map(|r| r.address()).min() {
+            Some(start) => start,
+            None => return Ok(Vec::new()),
+        };
+        let end = regs
+            .iter()
+            .map(|r| r.address() + r.length())
+            .max()
+            .unwrap_or(start);
 
-#[derive(Debug)]
-struct InstructionPacket {
-    id: u8,
-    length: u16,
-    instruction: Instruction,
-    parameters: Vec<u8>,
-}
-impl InstructionPacket {
-    fn new(id: u8, instruction: Instruction, parameters: Vec<u8>) -> InstructionPacket {
-        InstructionPacket {
-            id,
-            length: (parameters.len() + 3) as u16,
-            instruction,
-            parameters,
-        }
-    }
-    fn ping(id: u8) -> InstructionPacket {
-        InstructionPacket::new(id, Instruction::Ping, vec![])
-    }
-    fn read_data(id: u8, addr: u16, len: u16) -> InstructionPacket {
-        let (addr_l, addr_h) = unpack!(addr);
-        let (len_l, len_h) = unpack!(len);
+        let bytes = self.read_bytes(id, start, end - start)?;
 
-        InstructionPacket::new(
-            id,
-            Instruction::ReadData,
-            vec![addr_l, addr_h, len_l, len_h],
-        )
+        regs.iter()
+            .map(|r| {
+                let offset = (r.address() - start) as usize;
+                dxl_decode_data!(r.length(), bytes[offset..])
+            })
+            .collect()
     }
-    fn sync_read_data(ids: &[u8], addr: u16, len: u16) -> InstructionPacket {
-        let (addr_l, addr_h) = unpack!(addr);
-        let (len_l, len_h) = unpack!(len);
+    /// Read the present position of motor `id` in degrees, using the conversion of the motor model `M`.
+    ///
+    /// The position is read as a signed value, so multi-turn positions (e.g. below zero in extended-position mode) are converted as is.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_present_position_degrees<M>(&mut self, id: u8) -> Result<f32, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let raw = self.read_present_position_signed::<M>(id)?;
 
-        let mut param = vec![addr_l, addr_h, len_l, len_h];
-        param.extend(ids);
+        Ok(M::position_to_degrees(raw))
+    }
+    /// Read the raw present position of motor `id` as a signed value, using the PresentPosition register of the motor model `M`.
+    ///
+    /// In extended-position (multi-turn) mode the position is a signed 4 bytes value which goes negative below zero, use this instead of `read_data` which decodes it as unsigned (e.g. -1 would be read as 4294967295).
+    /// In single-turn modes, both reads give the same value.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_present_position_signed<M>(&mut self, id: u8) -> Result<i32, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let raw = self.read_data(id, M::PRESENT_POSITION)?;
 
-        InstructionPacket::new(BROADCAST_ID, Instruction::SyncRead, param)
+        Ok(raw as i32)
     }
-    fn write_data(id: u8, addr: u16, len: u16, data: u16) -> InstructionPacket {
-        let (addr_l, addr_h) = unpack!(addr);
+    /// Read the present velocity and position (both raw and signed) of motor `id` in a single transaction, using the registers of the motor model `M`.
+    ///
+    /// On the X-series, PresentVelocity (0x80) and PresentPosition (0x84) are adjacent so a single 8 bytes read fetches both, halving the transactions of a velocity-aware control loop.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_velocity_and_position<M>(&mut self, id: u8) -> Result<(i32, i32), DynamixelError>
+    where
+        M: VelocityControl,
+    {
+        let values = self.read_registers(id, &[M::PRESENT_VELOCITY, M::PRESENT_POSITION])?;
 
-        let mut parameters = vec![addr_l, addr_h];
-        parameters.extend(dxl_code_data!(len, data));
-        InstructionPacket::new(id, Instruction::WriteData, parameters)
+        Ok((values[0] as i32, values[1] as i32))
     }
-    fn sync_write_data(addr: u16, len: u16, data: &[(u8, u16)]) -> InstructionPacket {
-        let (addr_l, addr_h) = unpack!(addr);
-        let (len_l, len_h) = unpack!(len);
+    /// Read the present temperature of motor `id` in °C, using the PresentTemperature register of the motor model `M`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_temperature<M>(&mut self, id: u8) -> Result<i16, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let raw = self.read_data(id, M::PRESENT_TEMPERATURE)?;
 
-        let mut param = vec![addr_l, addr_h, len_l, len_h];
+        Ok(raw as i16)
+    }
+    /// Read the present input voltage of motor `id` in volts, using the PresentVoltage register of the motor model `M`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_voltage<M>(&mut self, id: u8) -> Result<f32, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let raw = self.read_data(id, M::PRESENT_VOLTAGE)?;
 
-        let coded_data = data.iter().fold(Vec::new(), |mut acc, &(id, data)| {
-            acc.push(id);
-            acc.extend(dxl_code_data!(len, data));
-            acc
-        });
+        Ok(raw as f32 * VOLTS_PER_UNIT)
+    }
+    /// Read the present input voltage (V) and temperature (°C) of motor `id` in a single transaction, using the registers of the motor model `M`.
+    ///
+    /// The two registers are adjacent: 0x2D-0x2E (2 bytes) on the XL-320, 0x90-0x92 (3 bytes) on the X-series.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_health<M>(&mut self, id: u8) -> Result<Health, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let values = self.read_registers(id, &[M::PRESENT_VOLTAGE, M::PRESENT_TEMPERATURE])?;
 
-        param.extend(coded_data);
+        Ok(Health {
+            voltage: values[0] as f32 * VOLTS_PER_UNIT,
+            temperature: values[1] as i16,
+        })
+    }
+    /// Read the present current of motor `id` in mA, using the PresentCurrent register of the motor model `M`.
+    ///
+    /// The current is signed: counter-clockwise currents are positive and clockwise currents are negative.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_current<M>(&mut self, id: u8) -> Result<i16, DynamixelError>
+    where
+        M: CurrentSensing,
+    {
+        let raw = self.read_data(id, M::PRESENT_CURRENT)? as u16 as i16;
+        let milliamps = f32::from(raw) * M::MILLIAMPS_PER_UNIT;
+        let milliamps = if milliamps < 0.0 {
+            milliamps - 0.5
+        } else {
+            milliamps + 0.5
+        };
 
-        InstructionPacket::new(BROADCAST_ID, Instruction::SyncWrite, param)
+        // The extreme raw values do not fit in an i16 once converted (e.g. 2.69mA per unit on the XM430).
+        Ok(if milliamps >= 32_767.0 {
+            32_767
+        } else if milliamps <= -32_768.0 {
+            -32_768
+        } else {
+            milliamps as i16
+        })
     }
-    /// [0xFF, 0xFF, 0xFD, 0x00, ID, LEN_L, LEN_H, INST, PARAM 1, PARAM 2, ..., PARAM N, CRC_L, CRC_H]
-    fn as_bytes(&self) -> Vec<u8> {
-        let (len_l, len_h) = unpack!(self.length);
+    /// Read and decode the hardware errors latched on motor `id`, using the HardwareErrorStatus register of the motor model `M`.
+    ///
+    /// It is meant to be called once an `ErrorType::Alert` is reported. The alert bit of its own StatusPacket is ignored.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_hardware_error<M>(&mut self, id: u8) -> Result<HardwareError, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let ignore_alert = mem::replace(&mut self.ignore_alert, true);
+        let raw = self.read_data(id, M::HARDWARE_ERROR_STATUS);
+        self.ignore_alert = ignore_alert;
 
-        let mut buff = vec![
-            0xFF,
-            0xFF,
-            0xFD,
-            0x00,
-            self.id,
+        Ok(M::decode_hardware_error(raw? as u8))
+    }
+    /// Check whether motor `id` is moving, using the Moving register of the motor model `M`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn is_moving<M>(&mut self, id: u8) -> Result<bool, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        Ok(self.read_data(id, M::MOVING)? != 0)
+    }
+    /// Wait until motor `id` stops moving, using the Moving register of the motor model `M`.
+    ///
+    /// The Moving register is read every `poll_interval` until the motor reports it stopped. A still moving error is returned if it is still moving after `timeout`,
+    /// while a timeout error means the motor stopped answering (as for any other read).
+    ///
+    /// *Note: This will block until the motor stops or the timeout elapses.*
+    pub fn wait_until_stopped<M>(
+        &mut self,
+        id: u8,
+        timeout: hal::time::MilliSecond,
+        poll_interval: hal::time::MilliSecond,
+    ) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let start = self.clock.now();
+
+        loop {
+            if !self.is_moving::<M>(id)? {
+                return Ok(());
+            }
+            if self.clock.now() - start > timeout {
+                return Err(DynamixelError::still_moving());
+            }
+
+            self.wait(poll_interval);
+        }
+    }
+    /// Move motor `id` to the raw position `goal` and wait until it gets there, using the GoalPosition and PresentPosition registers of the motor model `M`.
+    ///
+    /// The PresentPosition is read every `poll_interval` until it is within `tolerance` of `goal`, and then returned.
+    /// The positions are compared as signed values, so the negative positions of the extended position mode are supported.
+    /// A not in position error is returned if the motor is not in position after `timeout` (e.g. it is stalled),
+    /// while a timeout error means the motor stopped answering (as for any other read).
+    ///
+    /// *Note: Each poll is a full read transaction, a too short `poll_interval` saturates the bus and delays the other motors.*
+    pub fn go_to_position<M>(
+        &mut self,
+        id: u8,
+        goal: u32,
+        tolerance: u32,
+        timeout: hal::time::MilliSecond,
+        poll_interval: hal::time::MilliSecond,
+    ) -> Result<u32, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        self.write_data(id, M::GOAL_POSITION, goal)?;
+        let start = self.clock.now();
+
+        loop {
+            let present = self.read_data(id, M::PRESENT_POSITION)?;
+            if (i64::from(present as i32) - i64::from(goal as i32)).abs() <= i64::from(tolerance) {
+                return Ok(present);
+            }
+            if self.clock.now() - start > timeout {
+                return Err(DynamixelError::not_in_position());
+            }
+
+            self.wait(poll_interval);
+        }
+    }
+    /// Move motor `id` to the raw position `goal` by steps of at most `step`, waiting `delay` between each step.
+    ///
+    /// The GoalPosition is ramped from the present position so the motor never jumps to a far away goal at full speed.
+    /// The positions are ramped as signed values, so the negative positions of the extended position mode are supported.
+    /// This is only a software ramp, smooth trapezoidal moves should rather use the profile registers of the motor when it has them.
+    ///
+    /// *Note: This will block until the last GoalPosition has been acknowledged (not until the motor reaches it).*
+    pub fn move_to_smoothly<M>(
+        &mut self,
+        id: u8,
+        goal: u32,
+        step: u32,
+        delay: hal::time::MilliSecond,
+    ) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        if step == 0 {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        let target = i64::from(goal as i32);
+        let step = i64::from(step);
+        let mut position = i64::from(self.read_data(id, M::PRESENT_POSITION)? as i32);
+
+        while position != target {
+            position = if target > position {
+                cmp::min(position + step, target)
+            } else {
+                cmp::max(position - step, target)
+            };
+            // The position stays between the present position and the goal, both fit in an i32.
+            self.write_data(id, M::GOAL_POSITION, position as i32 as u32)?;
+
+            if position != target {
+                self.wait(delay);
+            }
+        }
+
+        Ok(())
+    }
+    /// Write `data` to a specified register `REG` on motor `id`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment. A write to the broadcast id returns as soon as it is sent, as no motor answers it.*
+    pub fn write_data<ID, REG>(
+        &mut self,
+        id: ID,
+        reg: &REG,
+        data: u32,
+    ) -> Result<(), DynamixelError>
+    where
+        ID: Into<MotorId>,
+        REG: Register + ?Sized,
+    {
+        let result = self.write_register(u8::from(id.into()), reg.address(), reg.length(), data);
+        self.eeprom_guarded(reg, result)
+    }
+    /// Write the raw bytes `data` starting at address `addr` on motor `id`.
+    ///
+    /// Contrary to `write_data`, the bytes are sent without any encoding so any length can be written (e.g. several adjacent registers or an indirect data block).
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn write_bytes<ID>(&mut self, id: ID, addr: u16, data: &[u8]) -> Result<(), DynamixelError>
+    where
+        ID: Into<MotorId>,
+    {
+        self.acknowledged(&InstructionPacket::with_address::<P>(
+            u8::from(id.into()),
+            Instruction::WriteData,
+            addr,
+            data,
+        ))
+    }
+    /// Write `data` to a register `REG` whose type is known at compile time on motor `id`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn write_typed<ID, REG>(
+        &mut self,
+        id: ID,
+        reg: &REG,
+        data: REG::Data,
+    ) -> Result<(), DynamixelError>
+    where
+        ID: Into<MotorId>,
+        REG: TypedRegister,
+    {
+        let result = self.write_register(
+            u8::from(id.into()),
+            reg.address(),
+            REG::Data::LENGTH,
+            data.to_raw(),
+        );
+        self.eeprom_guarded(reg, result)
+    }
+    /// Write `data` to a specified register `REG` on motor `id` and read it back to make sure the value was taken into account.
+    ///
+    /// A verification failed error is returned if the value read back does not match. This is worth the extra round-trip for critical writes (e.g. EEPROM configuration).
+    ///
+    /// *Note: This can not be used on the ID register as the motor answers with its new id, see `set_id` instead.*
+    pub fn write_data_verified<REG>(
+        &mut self,
+        id: u8,
+        reg: &REG,
+        data: u32,
+    ) -> Result<(), DynamixelError>
+    where
+        REG: Register + ?Sized,
+    {
+        self.write_data(id, reg, data)?;
+
+        if self.read_data(id, reg)? != data {
+            return Err(DynamixelError::verification_failed());
+        }
+
+        Ok(())
+    }
+    /// Update the register `REG` on motor `id` with the value returned by `f` applied to its current value.
+    ///
+    /// This is meant for bitfield registers, where only some bits should be changed without clobbering the others.
+    ///
+    /// *Note: This will read the register then write it back, blocking until both StatusPackets are received.*
+    pub fn update_data<REG, F>(&mut self, id: u8, reg: &REG, f: F) -> Result<(), DynamixelError>
+    where
+        REG: Register + ?Sized,
+        F: FnOnce(u32) -> u32,
+    {
+        let current = self.read_data(id, reg)?;
+
+        self.write_data(id, reg, f(current))
+    }
+    /// Change the id of motor `current_id` to `new_id`, using the ID register of the motor model `M`.
+    ///
+    /// An invalid value error is returned if `current_id` is the broadcast id (all motors would get the same id) or if `new_id` is not a valid motor id (0-252), nothing is written in this case.
+    /// As the ID register is in the EEPROM, the torque is disabled first and must be enabled again afterwards.
+    /// The change is confirmed by pinging `new_id`, a verification failed error is returned if the motor does not answer.
+    pub fn set_id<M>(&mut self, current_id: u8, new_id: u8) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        if current_id == BROADCAST_ID || new_id > MAX_ID - 1 {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = encode_write::<P>(
+            current_id,
+            M::ID.address(),
+            M::ID.length(),
+            u32::from(new_id),
+            &mut buff,
+        )?;
+        self.disable_torque::<M>(current_id)?;
+        // Values cached for a motor which previously had the new id are stale too.
+        self.invalidate_cache(current_id);
+        self.invalidate_cache(new_id);
+
+        let acknowledged = self
+            .expected_level(current_id)
+            .answers(Instruction::WriteData as u8);
+        let result = self.retried(|c| {
+            c.send_bytes(&buff[..length])?;
+            if acknowledged {
+                // Depending on the firmware, the acknowledgment comes from the previous or the new id.
+                let ack = c.recv_packet(BROADCAST_ID)?;
+                if ack.id != current_id && ack.id != new_id {
+                    return Err(DynamixelError::parsing_error());
+                }
+                P::checked(ack, c.ignore_alert)?;
+            }
+            Ok(())
+        });
+        self.eeprom_guarded(M::ID, result)?;
+
+        if let Some(level) = self.status_return_levels.remove(&current_id) {
+            self.status_return_levels.insert(new_id, level);
+        }
+
+        if !self.ping(new_id)? {
+            return Err(DynamixelError::verification_failed());
+        }
+
+        Ok(())
+    }
+    /// Map the indirect `slot` (starting at 1) of motor `id` to the control table address `target_addr`.
+    ///
+    /// The byte at `target_addr` is then accessible at the matching byte of the IndirectData block (see `read_indirect`/`write_indirect`).
+    /// Multi-byte registers need one slot per byte (e.g. slots 1-4 mapped to PresentPosition to PresentPosition + 3).
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn map_indirect<M>(
+        &mut self,
+        id: u8,
+        slot: u16,
+        target_addr: u16,
+    ) -> Result<(), DynamixelError>
+    where
+        M: IndirectAddressing,
+    {
+        if slot == 0 || slot > M::INDIRECT_SLOTS {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        let addr = M::INDIRECT_ADDRESS + 2 * (slot - 1);
+        self.write_register(id, addr, 2, u32::from(target_addr))
+    }
+    /// Read the first `len` bytes of the IndirectData block of motor `id`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_indirect<M>(&mut self, id: u8, len: u16) -> Result<Vec<u8>, DynamixelError>
+    where
+        M: IndirectAddressing,
+    {
+        if len > M::INDIRECT_SLOTS {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.read_bytes(id, M::INDIRECT_DATA, len)
+    }
+    /// Write `data` to the beginning of the IndirectData block of motor `id`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn write_indirect<M>(&mut self, id: u8, data: &[u8]) -> Result<(), DynamixelError>
+    where
+        M: IndirectAddressing,
+    {
+        if data.len() > M::INDIRECT_SLOTS as usize {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.write_bytes(id, M::INDIRECT_DATA, data)
+    }
+    /// Set the baud rate of motor `id` to `baud`.
+    ///
+    /// The value written to the BaudRate register depends on the motor model `M`. An invalid value error is returned if the model does not support this baud rate.
+    ///
+    /// *Note: The motor acknowledges at its previous baud rate and switches right after. The host serial port must be reconfigured separately to keep communicating with it, then `resync` checks the new connection.*
+    pub fn set_baud_rate<M>(&mut self, id: u8, baud: BaudRate) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let index = M::baud_rate_index(baud).ok_or_else(DynamixelError::invalid_value)?;
+
+        self.write_data(id, M::BAUD_RATE, u32::from(index))
+    }
+    /// Check that motor `id` answers again after a baud rate change.
+    ///
+    /// It waits a short grace period for the motor to switch, then pings it with a longer timeout than usual.
+    /// The timeout error of the ping is returned if the motor does not answer, which usually means the host serial port was not reconfigured to the new baud rate.
+    ///
+    /// ## Example
+    ///
+    /// ```ignore
+    /// c.set_baud_rate::<XL430>(1, BaudRate::B1M)?;
+    /// // reconfigure the host serial port at 1 Mbps
+    /// c.resync(1)?;
+    /// ```
+    ///
+    /// *Note: This will block during the grace period then until the StatusPacket is received or the timeout elapses.*
+    pub fn resync(&mut self, id: u8) -> Result<(), DynamixelError> {
+        self.wait(RESYNC_GRACE_PERIOD);
+
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = P::frame_into(id, &[Instruction::Ping as u8], &mut buff)?;
+
+        let timeout = mem::replace(&mut self.timeout, RESYNC_TIMEOUT);
+        let answer = self.request_bytes(id, &buff[..length]);
+        self.timeout = timeout;
+
+        answer.map(|_| ())
+    }
+    /// Set which hardware errors make motor `id` disable its torque, using the Shutdown register of the motor model `M`.
+    ///
+    /// For instance, leaving out `HardwareError::OVERLOAD` keeps the torque on during a recoverable stall. The errors not supported by the model are ignored.
+    ///
+    /// **Warning:** The Shutdown register is in the EEPROM, so the torque is disabled first and must be enabled again afterwards. The EEPROM has a limited number of write cycles, this is meant to be configured once rather than at each start.
+    ///
+    /// *Note: This will send two InstructionPackets to the motor and block until their StatusPackets are received as acknowledgments.*
+    pub fn set_shutdown_config<M>(
+        &mut self,
+        id: u8,
+        config: ShutdownConfig,
+    ) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        self.disable_torque::<M>(id)?;
+        self.write_data(id, M::SHUTDOWN, u32::from(M::encode_hardware_error(config)))
+    }
+    /// Set the LED of motor `id` to `color`, using the LED register of the motor model `M`.
+    ///
+    /// An invalid value error is returned if the LED of the model can not show this color (e.g. the X-series only have a red LED).
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn set_led<M>(&mut self, id: u8, color: LedColor) -> Result<(), DynamixelError>
+    where
+        M: Led,
+    {
+        let value = M::led_value(color).ok_or_else(DynamixelError::invalid_value)?;
+
+        self.write_data(id, M::LED, u32::from(value))
+    }
+    /// Enable the torque of motor `id`, using the TorqueEnable register of the motor model `M`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn enable_torque<M>(&mut self, id: u8) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        self.write_data(id, M::TORQUE_ENABLE, 1)
+    }
+    /// Disable the torque of motor `id`, using the TorqueEnable register of the motor model `M`.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn disable_torque<M>(&mut self, id: u8) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        self.write_data(id, M::TORQUE_ENABLE, 0)
+    }
+    /// Set the operating mode of motor `id`, using the OperatingMode register of the motor model `M`.
+    ///
+    /// As the OperatingMode register is in the EEPROM, the torque is disabled first and must be enabled again afterwards.
+    /// An invalid value error is returned if the model does not support the `mode`.
+    ///
+    /// *Note: This will send two InstructionPackets to the motor and block until their StatusPackets are received as acknowledgments.*
+    pub fn set_operating_mode<M>(
+        &mut self,
+        id: u8,
+        mode: OperatingMode,
+    ) -> Result<(), DynamixelError>
+    where
+        M: OperatingModes,
+    {
+        if !M::supports_operating_mode(mode) {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.disable_torque::<M>(id)?;
+        self.write_data(id, M::OPERATING_MODE, mode as u32)
+    }
+    /// Restrict the goal positions of motor `id` to `min`-`max` (raw positions), using the position limit registers of the motor model `M`.
+    ///
+    /// The registers differ between the families (MinPositionLimit/MaxPositionLimit on the X-series, CWAngleLimit/CCWAngleLimit on the XL-320).
+    /// As they are in the EEPROM, the torque is disabled first and must be enabled again afterwards. An invalid value error is returned if `min` is not below `max`.
+    ///
+    /// *Note: This will send three InstructionPackets to the motor and block until their StatusPackets are received as acknowledgments.*
+    pub fn set_position_limits<M>(
+        &mut self,
+        id: u8,
+        min: u32,
+        max: u32,
+    ) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        if min >= max {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.disable_torque::<M>(id)?;
+        self.write_data(id, M::MIN_POSITION_LIMIT, min)?;
+        self.write_data(id, M::MAX_POSITION_LIMIT, max)
+    }
+    /// Set the maximum velocity of the position moves of motor `id`, using the ProfileVelocity register of the motor model `M`.
+    ///
+    /// With the profile acceleration, it makes the motor follow a smooth trapezoidal velocity profile instead of moving at full speed. A velocity of 0 means no limit.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn set_profile_velocity<M>(&mut self, id: u8, velocity: u32) -> Result<(), DynamixelError>
+    where
+        M: VelocityProfile,
+    {
+        self.write_data(id, M::PROFILE_VELOCITY, velocity)
+    }
+    /// Set the acceleration of the position moves of motor `id`, using the ProfileAcceleration register of the motor model `M`.
+    ///
+    /// An acceleration of 0 means no limit.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn set_profile_acceleration<M>(
+        &mut self,
+        id: u8,
+        acceleration: u32,
+    ) -> Result<(), DynamixelError>
+    where
+        M: VelocityProfile,
+    {
+        self.write_data(id, M::PROFILE_ACCELERATION, acceleration)
+    }
+    /// Set the goal current of motor `id` in mA, using the GoalCurrent register of the motor model `M`.
+    ///
+    /// The current is signed: counter-clockwise currents are positive and clockwise currents are negative. An invalid value error is returned if it is above the current limit of the model.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn write_goal_current<M>(&mut self, id: u8, milliamps: i16) -> Result<(), DynamixelError>
+    where
+        M: CurrentSensing,
+    {
+        let raw = f32::from(milliamps) / M::MILLIAMPS_PER_UNIT;
+        let raw = (if raw < 0.0 { raw - 0.5 } else { raw + 0.5 }) as i16;
+
+        self.write_signed(id, M::GOAL_CURRENT, raw, M::MAX_CURRENT)
+    }
+    /// Set the goal PWM of motor `id`, using the GoalPWM register of the motor model `M`.
+    ///
+    /// The PWM is signed: counter-clockwise is positive and clockwise is negative. An invalid value error is returned if it is above the PWM limit of the model.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn write_goal_pwm<M>(&mut self, id: u8, pwm: i16) -> Result<(), DynamixelError>
+    where
+        M: PwmControl,
+    {
+        self.write_signed(id, M::GOAL_PWM, pwm, M::MAX_PWM)
+    }
+    /// Set the goal velocity (raw) of motor `id`, using the GoalVelocity register of the motor model `M`.
+    ///
+    /// The velocity is signed: counter-clockwise is positive and clockwise is negative. An invalid value error is returned if it is above the velocity limit of the model.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn write_goal_velocity<M>(&mut self, id: u8, velocity: i32) -> Result<(), DynamixelError>
+    where
+        M: VelocityControl,
+    {
+        if i64::from(velocity).abs() > i64::from(M::MAX_VELOCITY) {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.write_data(id, M::GOAL_VELOCITY, velocity as u32)
+    }
+    /// Read the PWM applied by motor `id`, using the PresentPWM register of the motor model `M`.
+    ///
+    /// The PWM is signed like the goal PWM. A magnitude reaching the PWM limit of the model means the motor is saturated.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_present_pwm<M>(&mut self, id: u8) -> Result<i16, DynamixelError>
+    where
+        M: PwmControl,
+    {
+        Ok(self.read_data(id, M::PRESENT_PWM)? as u16 as i16)
+    }
+    /// Read the realtime tick (in ms) of motor `id`, using the RealtimeTick register of the motor model `M`.
+    ///
+    /// The tick wraps to 0 after `M::MAX_TICK` (32767 for the X-series, about 33s), so only differences between close readings are meaningful, e.g. to estimate the latency or detect dropped control loops.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_tick<M>(&mut self, id: u8) -> Result<u16, DynamixelError>
+    where
+        M: RealtimeTick,
+    {
+        Ok(self.read_data(id, M::REALTIME_TICK)? as u16)
+    }
+    /// Set the PID gains of the position controller of motor `id`, using the gain registers of the motor model `M`.
+    ///
+    /// The gains are raw register values (see the `PositionGains` implementation of the model for their units), an invalid value error is returned if one is above `M::MAX_GAIN`.
+    /// On the X-series the three registers are adjacent, so they are written with a single instruction.
+    ///
+    /// *Note: This will send InstructionPackets to the motor and block until the StatusPackets are received as acknowledgments.*
+    pub fn set_position_gains<M>(
+        &mut self,
+        id: u8,
+        p: u16,
+        i: u16,
+        d: u16,
+    ) -> Result<(), DynamixelError>
+    where
+        M: PositionGains,
+    {
+        self.write_gains(
+            id,
+            M::MAX_GAIN,
+            &mut [
+                (M::POSITION_P_GAIN, p),
+                (M::POSITION_I_GAIN, i),
+                (M::POSITION_D_GAIN, d),
+            ],
+        )
+    }
+    /// Set the velocity (1st) and acceleration (2nd) feedforward gains of the position controller of motor `id`, using the registers of the motor model `M`.
+    ///
+    /// The gains are raw register values, an invalid value error is returned if one is above `M::MAX_GAIN`.
+    ///
+    /// *Note: This will send InstructionPackets to the motor and block until the StatusPackets are received as acknowledgments.*
+    pub fn set_feedforward_gains<M>(
+        &mut self,
+        id: u8,
+        first: u16,
+        second: u16,
+    ) -> Result<(), DynamixelError>
+    where
+        M: PositionGains,
+    {
+        self.write_gains(
+            id,
+            M::MAX_GAIN,
+            &mut [
+                (M::FEEDFORWARD_1ST_GAIN, first),
+                (M::FEEDFORWARD_2ND_GAIN, second),
+            ],
+        )
+    }
+    /// Write the gain registers of motor `id`, with a single instruction if they are adjacent.
+    fn write_gains(
+        &mut self,
+        id: u8,
+        max: u16,
+        gains: &mut [(&'static dyn Register, u16)],
+    ) -> Result<(), DynamixelError> {
+        if gains.iter().any(|&(_, gain)| gain > max) {
+            return Err(DynamixelError::invalid_value());
+        }
+        gains.sort_unstable_by_key(|&(reg, _)| reg.address());
+
+        let adjacent = gains
+            .windows(2)
+            .all(|w| w[0].0.address() + w[0].0.length() == w[1].0.address());
+        if !adjacent {
+            for &(reg, gain) in gains.iter() {
+                self.write_data(id, reg, u32::from(gain))?;
+            }
+            return Ok(());
+        }
+
+        let mut data = Vec::new();
+        for &(reg, gain) in gains.iter() {
+            data.extend((0..reg.length()).map(|i| (u32::from(gain) >> (8 * i)) as u8));
+        }
+        self.write_bytes(id, gains[0].0.address(), &data)
+    }
+    /// Read and decode the MovingStatus of motor `id`, using the register of the motor model `M`.
+    ///
+    /// Unlike the Moving register, it tells whether the profile is over and the goal position reached, e.g. `read_moving_status::<XL430>(id)?.is_done()` to detect the end of a motion.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse.*
+    pub fn read_moving_status<M>(&mut self, id: u8) -> Result<MovingStatus, DynamixelError>
+    where
+        M: MotionStatus,
+    {
+        Ok(MovingStatus::from_raw(
+            self.read_data(id, M::MOVING_STATUS)? as u8,
+        ))
+    }
+    /// Set the delay (in µs) before motor `id` answers an instruction, using the ReturnDelayTime register of the motor model `M`.
+    ///
+    /// The delay is rounded to the register unit of the model (2µs for the XL-320 and the X-series, up to 508µs), an invalid value error is returned if it is above the maximum.
+    /// A delay of 0 gives the fastest control loops, a small delay helps on buses where the controller needs time to switch to reception.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn set_return_delay<M>(&mut self, id: u8, micros: u16) -> Result<(), DynamixelError>
+    where
+        M: MotorModel,
+    {
+        let unit = M::RETURN_DELAY_MICROS_PER_UNIT;
+        let value = (u32::from(micros) + u32::from(unit) / 2) / u32::from(unit);
+        if value > u32::from(MAX_RETURN_DELAY) {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.write_data(id, M::RETURN_DELAY_TIME, value)
+    }
+    /// Busy wait for `duration`.
+    pub(crate) fn wait(&self, duration: hal::time::MilliSecond) {
+        let start = self.clock.now();
+        while self.clock.now() - start < duration {}
+    }
+    /// Ping motor `id` and return its StatusPacket, `None` if it does not answer.
+    fn ping_status(&mut self, id: u8) -> Result<Option<StatusPacket>, DynamixelError> {
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = P::frame_into(id, &[Instruction::Ping as u8], &mut buff)?;
+
+        match self.request_bytes(id, &buff[..length]) {
+            Ok(status) => Ok(Some(status)),
+            Err(DynamixelError {
+                error: ErrorType::Timeout { .. },
+            }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    /// The StatusPacket was lost (timeout) or corrupted (invalid checksum, if enabled) so the instruction can be sent again.
+    fn retryable(&self, e: &DynamixelError) -> bool {
+        match e.error {
+            ErrorType::Timeout { .. } => true,
+            ErrorType::InvalidChecksum => self.retry_invalid_checksum,
+            _ => false,
+        }
+    }
+    /// Send `packet` and wait for its StatusPacket, sending it again on timeout up to `retries` times.
+    fn request(&mut self, packet: &InstructionPacket) -> Result<StatusPacket, DynamixelError> {
+        self.request_bytes(packet.id, &packet.encode::<P>()?)
+    }
+    fn request_bytes(&mut self, id: u8, bytes: &[u8]) -> Result<StatusPacket, DynamixelError> {
+        self.retried(|c| {
+            c.send_bytes(bytes)?;
+            c.recv(id)
+        })
+    }
+    /// Run the transaction `f`, running it again on a retryable error up to `retries` times.
+    fn retried<T, F>(&mut self, mut f: F) -> Result<T, DynamixelError>
+    where
+        F: FnMut(&mut Self) -> Result<T, DynamixelError>,
+    {
+        let mut attempts = 0;
+
+        loop {
+            match f(self) {
+                Err(ref e) if attempts < self.retries && self.retryable(e) => {
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Send `packet` and wait for the acknowledgment only if the status return level says the motor will answer.
+    fn acknowledged(&mut self, packet: &InstructionPacket) -> Result<(), DynamixelError> {
+        self.acknowledged_bytes(packet.id, packet.instruction, &packet.encode::<P>()?)
+    }
+    fn acknowledged_bytes(
+        &mut self,
+        id: u8,
+        instruction: u8,
+        bytes: &[u8],
+    ) -> Result<(), DynamixelError> {
+        let level = self.expected_level(id);
+        self.invalidate_cache(id);
+
+        // No motor answers an instruction sent to the broadcast id (except a ping).
+        if id != BROADCAST_ID && level.answers(instruction) {
+            self.request_bytes(id, bytes)?;
+        } else {
+            self.send_bytes(bytes)?;
+        }
+
+        Ok(())
+    }
+    /// Status return level of motor `id`, the one set for this motor or the default one of the controller.
+    fn expected_level(&self, id: u8) -> StatusReturnLevel {
+        self.status_return_levels
+            .get(&id)
+            .cloned()
+            .unwrap_or(self.status_return_level)
+    }
+    /// Write `data` to the `len` bytes register at `addr`, the packet is built on the stack.
+    fn write_register(
+        &mut self,
+        id: u8,
+        addr: u16,
+        len: u16,
+        data: u32,
+    ) -> Result<(), DynamixelError> {
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = encode_write::<P>(id, addr, len, data, &mut buff)?;
+
+        self.acknowledged_bytes(id, Instruction::WriteData as u8, &buff[..length])
+    }
+    /// Write a signed 2 bytes `value` whose magnitude must not exceed `max`.
+    fn write_signed(
+        &mut self,
+        id: u8,
+        reg: &dyn Register,
+        value: i16,
+        max: u16,
+    ) -> Result<(), DynamixelError> {
+        if i32::from(value).abs() > i32::from(max) {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        self.write_data(id, reg, u32::from(value as u16))
+    }
+    /// Report the access error of a write to an EEPROM register as a torque error.
+    ///
+    /// The read-only registers (e.g. ModelNumber) always answer an access error, whatever the torque, so it is reported as is.
+    fn eeprom_guarded<REG>(
+        &self,
+        reg: &REG,
+        result: Result<(), DynamixelError>,
+    ) -> Result<(), DynamixelError>
+    where
+        REG: Register + ?Sized,
+    {
+        match result {
+            Err(DynamixelError {
+                error: ErrorType::StatusError(e),
+            }) if self.eeprom_guard
+                && reg.area() == RegisterArea::Eeprom
+                && !reg.is_read_only()
+                && e & !ALERT_BIT == ACCESS_ERROR =>
+            {
+                Err(DynamixelError::torque_must_be_disabled())
+            }
+            result => result,
+        }
+    }
+    fn send(&mut self, packet: &InstructionPacket) -> Result<(), DynamixelError> {
+        self.send_bytes(&packet.encode::<P>()?)
+    }
+    /// Send `bytes` on TX, a transmission error (or a transmitter still busy after `TX_TIMEOUT`) is reported instead of the packet being silently lost.
+    fn send_bytes(&mut self, bytes: &[u8]) -> Result<(), DynamixelError> {
+        if self.flush_before_transaction {
+            self.flush_input();
+        }
+        self.reception = None;
+        self.pending_read = None;
+        if self.timing_capture {
+            self.sent_at = Some(self.clock.now());
+        }
+
+        if let Some(ref mut de) = self.de {
+            de.set_high();
+        }
+
+        self.log_packet(Direction::Sent, bytes);
+        let mut sent = Ok(());
+        let mut written = 0;
+        for &b in bytes {
+            sent = self.transmit(|tx| tx.write(b));
+            if sent.is_err() {
+                break;
+            }
+            written += 1;
+        }
+        // Buffered UARTs may still hold the last bytes, they must be sent before waiting for the answer.
+        let mut sent = sent.and_then(|_| self.transmit(|tx| tx.flush()));
+        if self.de.is_some() {
+            // The last byte must be fully shifted out before releasing the bus.
+            sent = sent.and_then(|_| self.transmit(|tx| tx.complete()));
+        }
+        // The bus is released even if the sending failed, so the other devices can still use it.
+        if let Some(ref mut de) = self.de {
+            de.set_low();
+        }
+        // The bytes written before a failure are echoed too.
+        if self.echo_cancellation {
+            self.pending_echo += written;
+        }
+
+        sent
+    }
+    /// Retry the transmitter operation `op` while it would block, up to `TX_TIMEOUT`.
+    fn transmit<F>(&mut self, mut op: F) -> Result<(), DynamixelError>
+    where
+        F: FnMut(&mut TX) -> nb::Result<(), TX::Error>,
+    {
+        // The clock is only read once the transmitter is busy, sending is not slowed down otherwise.
+        let mut start = None;
+
+        loop {
+            match op(&mut self.tx) {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::WouldBlock) => {
+                    let now = self.clock.now();
+                    let start = *start.get_or_insert(now);
+                    if now - start > TX_TIMEOUT {
+                        return Err(DynamixelError::serial(SerialErrorKind::TxTimeout));
+                    }
+                }
+                Err(nb::Error::Other(e)) => return Err(DynamixelError::from(e.kind())),
+            }
+        }
+    }
+    fn recv(&mut self, id: u8) -> Result<StatusPacket, DynamixelError> {
+        let ignore_alert = self.ignore_alert;
+        P::checked(self.recv_packet(id)?, ignore_alert)
+    }
+    fn recv_packet(&mut self, id: u8) -> Result<StatusPacket, DynamixelError> {
+        block!(self.poll_packet())?.answer_of(id)
+    }
+    /// Read all the bytes available on RX and return the StatusPacket once it has been entirely received.
+    ///
+    /// The timeout applies to the time elapsed since the last received byte.
+    fn poll_packet(&mut self) -> nb::Result<StatusPacket, DynamixelError> {
+        let now = self.clock.now();
+        let mut reception = self.reception.take().unwrap_or(Reception {
+            decoder: StatusPacketDecoder::of_protocol(self.crc_check),
+            last_byte: now,
+        });
+
+        loop {
+            match self.rx.read() {
+                Ok(b) => {
+                    reception.last_byte = self.clock.now();
+
+                    if self.pending_echo > 0 {
+                        self.pending_echo -= 1;
+                        continue;
+                    }
+
+                    if let Some(bytes) = reception.decoder.frame(b)? {
+                        self.log_packet(Direction::Received, &bytes);
+                        let status = reception.decoder.parse(&bytes)?;
+                        self.crc_mismatch = reception.decoder.crc_mismatch();
+                        if let Some(sent_at) = self.sent_at {
+                            self.last_transaction_duration = Some(self.clock.now() - sent_at);
+                        }
+                        return Ok(status);
+                    }
+                }
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => {
+                    return Err(nb::Error::Other(DynamixelError::from(e.kind())));
+                }
+            }
+        }
+
+        if (self.clock.now() - reception.last_byte) > self.reception_timeout(&reception) {
+            // The missing echo will not come anymore, it must not eat the bytes of the next answer.
+            self.pending_echo = 0;
+            let received = reception.decoder.received();
+            return Err(nb::Error::Other(DynamixelError::timeout(received)));
+        }
+
+        self.reception = Some(reception);
+        Err(nb::Error::WouldBlock)
+    }
+    /// Time allowed since the last received byte, including the transmission time of the remaining bytes when the baud rate is known.
+    ///
+    /// The inter-byte timeout replaces the timeout once the header has been received.
+    fn reception_timeout(&self, reception: &Reception<P>) -> hal::time::MilliSecond {
+        let decoder = &reception.decoder;
+        let timeout = match self.inter_byte_timeout {
+            Some(timeout) if decoder.bytes.len() >= P::HEADER_LENGTH => timeout,
+            _ => self.timeout,
+        };
+        let bps = match self.baud_rate {
+            Some(baud) => baud.bps(),
+            None => return timeout,
+        };
+
+        let expected_length = match decoder.expected_length() {
+            Ok(Some(length)) => length,
+            _ => P::MIN_STATUS_LENGTH,
+        };
+        let remaining = expected_length.saturating_sub(decoder.bytes.len()) as u32;
+        // 10 bits per byte (start, 8 data bits and stop), plus 1ms for the truncated part
+        let transmission = remaining * 10 * 1000 / bps + 1;
+
+        hal::time::MilliSecond(timeout.0 + transmission)
+    }
+    #[cfg(feature = "std")]
+    fn log_packet(&mut self, direction: Direction, bytes: &[u8]) {
+        if let Some(ref mut logger) = self.packet_logger {
+            let (id, instruction, error) = P::packet_fields(bytes);
+            logger(&LoggedPacket {
+                direction,
+                id,
+                instruction,
+                error,
+                bytes,
+            });
+        }
+    }
+    #[cfg(not(feature = "std"))]
+    fn log_packet(&mut self, _direction: Direction, _bytes: &[u8]) {}
+}
+
+/// Instructions and answers only available in the protocol v2 (e.g. the sync read, the ping info or the fast sync read).
+impl<RX, TX, CLOCK, DE> ControllerV2<RX, TX, CLOCK, DE>
+where
+    TX: hal::serial::Write<u8>,
+    TX::Error: SerialError,
+    RX: hal::serial::Read<u8>,
+    RX::Error: SerialError,
+    CLOCK: hal::time::Time,
+    DE: hal::digital::OutputPin,
+{
+    /// Create a new controller for the protocol v2 on a half-duplex RS-485 bus.
+    ///
+    /// The driver-enable pin `de` is set high while sending an InstructionPacket and set low before waiting for the StatusPacket.
+    pub fn with_direction_pin(
+        rx: RX,
+        tx: TX,
+        clock: CLOCK,
+        de: DE,
+    ) -> ControllerV2<RX, TX, CLOCK, DE> {
+        ControllerV2Builder::new(rx, tx, clock).de_pin(de).build()
+    }
+    /// Send a ping to motor `id` and return its model number and firmware version (`None` if it does not answer).
+    pub fn ping_info(&mut self, id: u8) -> Result<Option<PingInfo>, DynamixelError> {
+        let status = match self.ping_status(id)? {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+        if status.parameters.len() != 3 {
+            return Err(DynamixelError::parsing_error());
+        }
+
+        Ok(Some(PingInfo {
+            model_number: pack!(status.parameters[0], status.parameters[1]),
+            firmware_version: status.parameters[2],
+        }))
+    }
+    /// Find all the motors on the bus with a single broadcast ping and return their ids and models.
+    ///
+    /// The motors answer one after the other in the slot of their id, so the answers are collected during a window covering all the ids (a truncated answer is still reported as a timeout error).
+    /// Answers which can not be decoded (wrong length or invalid checksum) are skipped, the other motors are still reported.
+    /// Models not supported by this crate are reported as `Model::Unknown` with their model number.
+    ///
+    /// *Note: This will send an InstructionPacket to all the motors and block until the end of the discovery window.*
+    pub fn discover(&mut self) -> Result<Vec<(u8, Model)>, DynamixelError> {
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = encode_ping(BROADCAST_ID, &mut buff)?;
+        self.send_bytes(&buff[..length])?;
+
+        let timeout = self.timeout;
+        let window = hal::time::MilliSecond(DISCOVERY_SLOT.0 * u32::from(MAX_ID));
+        let start = self.clock.now();
+
+        let mut motors = Vec::new();
+        let result = loop {
+            let elapsed = self.clock.now() - start;
+            if elapsed >= window {
+                break Ok(motors);
+            }
+            self.timeout = hal::time::MilliSecond(window.0 - elapsed.0);
+
+            // A hardware error latched on a motor (alert) does not prevent finding it.
+            match self.recv_packet(BROADCAST_ID) {
+                Ok(ref status) if status.parameters.len() == 3 => {
+                    let model_number = pack!(status.parameters[0], status.parameters[1]);
+                    motors.push((status.id, Model::from_number(model_number)));
+                }
+                Ok(_)
+                | Err(DynamixelError {
+                    error: ErrorType::InvalidChecksum,
+                }) => {}
+                Err(DynamixelError {
+                    error: ErrorType::Timeout { received: 0 },
+                }) => break Ok(motors),
+                Err(e) => break Err(e),
+            }
+        };
+
+        self.timeout = timeout;
+        result
+    }
+    /// Sync read data from a specified register `REG` on a list of motor `id`.
+    ///
+    /// Motors which do not answer (or send an answer of the wrong length) are left out of the result.
+    ///
+    /// *Note: This will send an InstructionPacket to all targeted motors and block until all the StatusPackets are received as reponse.*
+    pub fn sync_read_data<REG>(&mut self, ids: &[u8], reg: &REG) -> Vec<(u8, u32)>
+    where
+        REG: Register + ?Sized,
+    {
+        match InstructionPacket::sync_read_data(ids, reg.address(), reg.length()).as_bytes() {
+            Ok(packet) => self.sync_read_answers(&packet, ids, reg.length()),
+            Err(_) => Vec::new(),
+        }
+    }
+    /// Sync read data from a specified register `REG` on a list of motor `ids` and return the outcome for each motor, in the same order.
+    ///
+    /// Contrary to `sync_read_data`, the motors which fail are kept with their error, so a motor which does not answer (timeout) can be told from a motor answering with an error (e.g. `ErrorType::StatusError` or `ErrorType::Alert`).
+    ///
+    /// *Note: This will send an InstructionPacket to all targeted motors and block until all the StatusPackets are received as reponse.*
+    pub fn sync_read_results<REG>(
+        &mut self,
+        ids: &[u8],
+        reg: &REG,
+    ) -> Vec<(u8, Result<u32, DynamixelError>)>
+    where
+        REG: Register + ?Sized,
+    {
+        match InstructionPacket::sync_read_data(ids, reg.address(), reg.length()).as_bytes() {
+            Ok(packet) => self.sync_read_results_of(&packet, ids, reg.length()),
+            Err(e) => ids.iter().map(|&id| (id, Err(e.clone()))).collect(),
+        }
+    }
+    /// Sync read the present input voltage of the motors `ids` in volts, using the PresentVoltage register of the motor model `M`.
+    ///
+    /// This is meant to monitor the supply (e.g. a battery) across the whole bus. Motors which do not answer are left out of the result.
+    ///
+    /// *Note: This will send an InstructionPacket to all targeted motors and block until all the StatusPackets are received as reponse.*
+    pub fn sync_read_voltage<M>(&mut self, ids: &[u8]) -> Vec<(u8, f32)>
+    where
+        M: MotorModel,
+    {
+        self.sync_read_data(ids, M::PRESENT_VOLTAGE)
+            .into_iter()
+            .map(|(id, raw)| (id, raw as f32 * VOLTS_PER_UNIT))
+            .collect()
+    }
+    /// Create a `SyncGroup` to repeatedly sync read or write the register `REG` of the motors `ids`.
+    ///
+    /// The InstructionPackets skeletons are built once here instead of at each read/write.
+    pub fn sync_group<REG>(&self, ids: &[u8], reg: &REG) -> Result<SyncGroup, DynamixelError>
+    where
+        REG: Register + ?Sized,
+    {
+        let (addr_l, addr_h) = unpack!(reg.address());
+        let (len_l, len_h) = unpack!(reg.length());
+
+        Ok(SyncGroup {
+            ids: ids.to_vec(),
+            length: reg.length(),
+            read_packet: InstructionPacket::sync_read_data(ids, reg.address(), reg.length())
+                .as_bytes()?,
+            write_prefix: vec![addr_l, addr_h, len_l, len_h],
+        })
+    }
+    /// Fast sync read data from a specified register `REG` on a list of motor `ids`.
+    ///
+    /// Contrary to `sync_read_data`, all the motors answer within a single combined StatusPacket which removes most of the inter-packet delays.
+    /// Each answer is returned as `(id, error, value)` so a motor reporting an error does not discard the others.
+    ///
+    /// *Note: This will send an InstructionPacket to all targeted motors and block until the combined StatusPacket is received as response.*
+    pub fn fast_sync_read_data<REG>(
+        &mut self,
+        ids: &[u8],
+        reg: &REG,
+    ) -> Result<Vec<(u8, u8, u32)>, DynamixelError>
+    where
+        REG: Register + ?Sized,
+    {
+        let packet = InstructionPacket::fast_sync_read_data(ids, reg.address(), reg.length());
+        self.send(&packet)?;
+
+        let status = self.recv_packet(BROADCAST_ID)?;
+
+        status.fast_sync_read_segments(reg.length())
+    }
+    /// Reset the control table of motor `id` to its factory default values.
+    ///
+    /// **Warning:** This rewrites the EEPROM of the motor. Unless `scope` keeps them, the id is reset to 1 and the baud rate to its default value, so the motor may not be reachable anymore with the current settings.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn factory_reset(&mut self, id: u8, scope: ResetScope) -> Result<(), DynamixelError> {
+        self.acknowledged(&InstructionPacket::factory_reset(id, scope))
+    }
+    /// Reset the multi-turn revolution count of motor `id`.
+    ///
+    /// In extended-position (multi-turn) mode the present position accumulates revolutions. Clearing it brings the present position back within a single turn without moving the motor.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as an acknowledgment.*
+    pub fn clear_multi_turn(&mut self, id: u8) -> Result<(), DynamixelError> {
+        self.acknowledged(&InstructionPacket::clear_multi_turn(id))
+    }
+    /// Disable the torque of all the motors `ids`, e.g. at startup so nothing moves unexpectedly.
+    ///
+    /// The motors may be of different models: the model of each motor is read first and a SyncWrite is sent for each TorqueEnable address.
+    /// A motor whose model can not be resolved (absent, not answering or of an unknown model) does not keep the others from being disabled: the ids of these motors are returned so they can be handled separately.
+    /// If a SyncWrite can not be sent, the following ones are still sent and the first error is returned.
+    ///
+    /// *Note: This will read the ModelNumber of each motor, blocking until their StatusPackets are received, then send the SyncWrites without waiting for any answer.*
+    pub fn disable_all_torque(&mut self, ids: &[u8]) -> Result<Vec<u8>, DynamixelError> {
+        let mut regs = Vec::with_capacity(ids.len());
+        let mut unresolved = Vec::new();
+        for &id in ids {
+            match self
+                .read_model(id)
+                .ok()
+                .and_then(|model| model.torque_enable())
+            {
+                Some(reg) => regs.push((id, reg)),
+                None => unresolved.push(id),
+            }
+        }
+
+        let mut result = Ok(());
+        let mut written = Vec::new();
+        for &(_, reg) in &regs {
+            if written.contains(&reg.address()) {
+                continue;
+            }
+            written.push(reg.address());
+
+            let data: Vec<(u8, u32)> = regs
+                .iter()
+                .filter(|&&(_, r)| r.address() == reg.address())
+                .map(|&(id, _)| (id, 0))
+                .collect();
+            let sent = self.sync_write_data(reg, &data);
+            if result.is_ok() {
+                result = sent;
+            }
+        }
+
+        result.map(|_| unresolved)
+    }
+    /// Sync write `data` to a specified register `REG` on a list of motor `ids`.
+    ///
+    /// An invalid value error is returned, and nothing is sent, if a value does not fit in the register length.
+    ///
+    /// *Note: The motors will not answer after a SyncWrite. `sync_write_data` only blocks during the sending.*
+    pub fn sync_write_data<REG>(
+        &mut self,
+        reg: &REG,
+        data: &[(u8, u32)],
+    ) -> Result<(), DynamixelError>
+    where
+        REG: Register + ?Sized,
+    {
+        let (addr_l, addr_h) = unpack!(reg.address());
+        let (len_l, len_h) = unpack!(reg.length());
+
+        self.sync_write(
+            &[addr_l, addr_h, len_l, len_h],
+            reg.length(),
+            data.iter().cloned(),
+        )
+    }
+
+    /// Wait for the StatusPackets of a SyncRead sent to motors `ids`.
+    fn sync_read_answers(&mut self, packet: &[u8], ids: &[u8], len: u16) -> Vec<(u8, u32)> {
+        self.sync_read_results_of(packet, ids, len)
+            .into_iter()
+            .filter_map(|(id, result)| result.ok().map(|data| (id, data)))
+            .collect()
+    }
+    /// Send the SyncRead `packet` to motors `ids`, then wait for their StatusPackets and decode each of them.
+    ///
+    /// If the packet can not be sent, all the motors get the transmission error.
+    fn sync_read_results_of(
+        &mut self,
+        packet: &[u8],
+        ids: &[u8],
+        len: u16,
+    ) -> Vec<(u8, Result<u32, DynamixelError>)> {
+        if let Err(e) = self.send_bytes(packet) {
+            return ids.iter().map(|&id| (id, Err(e.clone()))).collect();
+        }
+
+        ids.iter()
+            .map(|&id| {
+                let result = self.recv(id).and_then(|status_packet| {
+                    if status_packet.parameters.len() != len as usize {
+                        return Err(DynamixelError::parsing_error());
+                    }
+                    dxl_decode_data!(len, status_packet.parameters)
+                });
+                (id, result)
+            })
+            .collect()
+    }
+    /// Send a SyncWrite of `data` with the parameters `prefix` ([ADDR_L, ADDR_H, LEN_L, LEN_H]).
+    ///
+    /// The packet is built in the controller buffers so a control loop does not allocate at each call.
+    fn sync_write<I>(&mut self, prefix: &[u8], len: u16, data: I) -> Result<(), DynamixelError>
+    where
+        I: Iterator<Item = (u8, u32)>,
+    {
+        sync_write_body(prefix, len, data, &mut self.body_buffer)?;
+
+        if self.eeprom_cache.is_some() {
+            let ids: Vec<u8> = self.body_buffer[1 + prefix.len()..]
+                .chunks(len as usize + 1)
+                .map(|motor| motor[0])
+                .collect();
+            for id in ids {
+                self.invalidate_cache(id);
+            }
+        }
+
+        frame::<V2>(BROADCAST_ID, &self.body_buffer, &mut self.packet_buffer)?;
+
+        let mut packet = Vec::new();
+        mem::swap(&mut packet, &mut self.packet_buffer);
+        let sent = self.send_bytes(&packet);
+        self.packet_buffer = packet;
+
+        sent
+    }
+}
+
+/// Set of motors whose same register is repeatedly sync read or written (e.g. in a control loop).
+///
+/// It is created with `ControllerV2::sync_group` and keeps the InstructionPackets skeletons so they are not rebuilt at each call.
+pub struct SyncGroup {
+    ids: Vec<u8>,
+    length: u16,
+    read_packet: Vec<u8>,
+    write_prefix: Vec<u8>,
+}
+impl SyncGroup {
+    /// Ids of the motors of the group.
+    pub fn ids(&self) -> &[u8] {
+        &self.ids
+    }
+    /// Sync read the register of all the motors of the group, see `ControllerV2::sync_read_data`.
+    ///
+    /// *Note: This will send an InstructionPacket to all motors of the group and block until all the StatusPackets are received as reponse.*
+    pub fn read<RX, TX, CLOCK, DE>(
+        &self,
+        controller: &mut ControllerV2<RX, TX, CLOCK, DE>,
+    ) -> Vec<(u8, u32)>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        controller.sync_read_answers(&self.read_packet, &self.ids, self.length)
+    }
+    /// Sync write `data` to the register of the motors of the group, `data` must contain one value per motor (in the order of `ids`).
+    ///
+    /// *Note: The motors will not answer after a SyncWrite. `write` only blocks during the sending.*
+    pub fn write<RX, TX, CLOCK, DE>(
+        &self,
+        controller: &mut ControllerV2<RX, TX, CLOCK, DE>,
+        data: &[u32],
+    ) -> Result<(), DynamixelError>
+    where
+        TX: hal::serial::Write<u8>,
+        TX::Error: SerialError,
+        RX: hal::serial::Read<u8>,
+        RX::Error: SerialError,
+        CLOCK: hal::time::Time,
+        DE: hal::digital::OutputPin,
+    {
+        if data.len() != self.ids.len() {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        controller.sync_write(
+            &self.write_prefix,
+            self.length,
+            self.ids.iter().cloned().zip(data.iter().cloned()),
+        )
+    }
+}
+
+/// StatusPacket being received by the controller.
+struct Reception<P> {
+    decoder: StatusPacketDecoder<P>,
+    last_byte: hal::time::MilliSecond,
+}
+
+/// Assemble StatusPackets from bytes fed one at a time.
+///
+/// It does not read anything by itself, so the bytes can come from any source (e.g. a buffer filled by an interrupt or a DMA transfer).
+///
+/// ## Example
+///
+/// ```ignore
+/// let mut decoder = StatusPacketDecoder::new(true);
+///
+/// for b in received_bytes {
+///     if let Some(status) = decoder.push(b)? {
+///         handle(status.id(), status.parameters());
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StatusPacketDecoder<P = V2> {
+    protocol: PhantomData<P>,
+    bytes: Vec<u8>,
+    skipped: usize,
+    check_crc: bool,
+    crc_mismatch: bool,
+}
+impl StatusPacketDecoder {
+    /// Create a decoder of the protocol v2, rejecting the packets with an invalid CRC if `check_crc` is set.
+    pub fn new(check_crc: bool) -> StatusPacketDecoder {
+        StatusPacketDecoder::of_protocol(check_crc)
+    }
+}
+impl<P> StatusPacketDecoder<P>
+where
+    P: Protocol,
+{
+    /// Create a decoder of the protocol `P`, rejecting the packets with an invalid checksum if `check_crc` is set.
+    pub fn of_protocol(check_crc: bool) -> StatusPacketDecoder<P> {
+        StatusPacketDecoder {
+            protocol: PhantomData,
+            bytes: Vec::new(),
+            skipped: 0,
+            check_crc,
+            crc_mismatch: false,
+        }
+    }
+    /// Add a received byte and return the StatusPacket once it is complete.
+    ///
+    /// The leading bytes that can not be the start of a header are dropped, which resynchronizes the decoding when stray bytes (noise, leftovers of a timed out packet) precede the StatusPacket.
+    ///
+    /// The decoder is ready for the next packet after a packet has been returned or an error reported.
+    pub fn push(&mut self, b: u8) -> Result<Option<StatusPacket>, DynamixelError> {
+        match self.frame(b)? {
+            Some(bytes) => Ok(Some(self.parse(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+    /// Whether the CRC of the last returned packet did not match, only possible when the CRC is not checked.
+    pub fn crc_mismatch(&self) -> bool {
+        self.crc_mismatch
+    }
+    /// Number of bytes received for the packet being decoded, including the dropped ones.
+    pub fn received(&self) -> usize {
+        self.bytes.len() + self.skipped
+    }
+    /// Drop the packet being decoded.
+    pub fn reset(&mut self) {
+        self.bytes.clear();
+        self.skipped = 0;
+    }
+    /// Add a received byte and return the raw bytes of the packet once it is complete.
+    fn frame(&mut self, b: u8) -> Result<Option<Vec<u8>>, DynamixelError> {
+        let framed = self.next_frame(b);
+        match framed {
+            Ok(None) => (),
+            _ => self.reset(),
+        }
+
+        framed
+    }
+    fn next_frame(&mut self, b: u8) -> Result<Option<Vec<u8>>, DynamixelError> {
+        self.bytes.push(b);
+
+        while !self.bytes.is_empty() && !P::is_header_start(&self.bytes) {
+            self.bytes.remove(0);
+            self.skipped += 1;
+        }
+
+        if self.skipped > MAX_SKIPPED_BYTES {
+            return Err(DynamixelError::parsing_error());
+        }
+
+        match self.expected_length()? {
+            Some(length) if self.bytes.len() == length => Ok(Some(self.bytes.clone())),
+            _ => Ok(None),
+        }
+    }
+    fn parse(&mut self, bytes: &[u8]) -> Result<StatusPacket, DynamixelError> {
+        let (status, valid_crc) = P::parse_status(bytes, self.check_crc)?;
+        self.crc_mismatch = !valid_crc;
+
+        Ok(status)
+    }
+    /// Length of the whole StatusPacket, known once its header has been received.
+    fn expected_length(&self) -> Result<Option<usize>, DynamixelError> {
+        if self.bytes.len() < P::HEADER_LENGTH {
+            return Ok(None);
+        }
+
+        Ok(Some(P::packet_length(&self.bytes[..P::HEADER_LENGTH])?))
+    }
+}
+
+/// Builder for a `ControllerV2` with a custom configuration.
+///
+/// By default, the controller uses a 1ms timeout, no retry, expects all the instructions to be answered and does not drive any direction pin.
+pub struct ControllerV2Builder<RX, TX, CLOCK, DE = NoDirectionPin> {
+    rx: RX,
+    tx: TX,
+    clock: CLOCK,
+    de: Option<DE>,
+
+    timeout: hal::time::MilliSecond,
+    inter_byte_timeout: Option<hal::time::MilliSecond>,
+    scan_timeout: Option<hal::time::MilliSecond>,
+    baud_rate: Option<BaudRate>,
+    retries: u8,
+    retry_invalid_checksum: bool,
+    status_return_level: StatusReturnLevel,
+}
+
+impl<RX, TX, CLOCK> ControllerV2Builder<RX, TX, CLOCK>
+where
+    TX: hal::serial::Write<u8>,
+    TX::Error: SerialError,
+    RX: hal::serial::Read<u8>,
+    RX::Error: SerialError,
+    CLOCK: hal::time::Time,
+{
+    /// Start building a controller for the protocol v2.
+    pub fn new(rx: RX, tx: TX, clock: CLOCK) -> ControllerV2Builder<RX, TX, CLOCK> {
+        ControllerV2Builder {
+            rx,
+            tx,
+            clock,
+            de: None,
+            timeout: TIMEOUT,
+            inter_byte_timeout: None,
+            scan_timeout: None,
+            baud_rate: None,
+            retries: RETRIES,
+            retry_invalid_checksum: true,
+            status_return_level: StatusReturnLevel::All,
+        }
+    }
+}
+
+impl<RX, TX, CLOCK, DE> ControllerV2Builder<RX, TX, CLOCK, DE>
+where
+    TX: hal::serial::Write<u8>,
+    TX::Error: SerialError,
+    RX: hal::serial::Read<u8>,
+    RX::Error: SerialError,
+    CLOCK: hal::time::Time,
+    DE: hal::digital::OutputPin,
+{
+    /// Time to wait for each byte of a StatusPacket before giving up.
+    ///
+    /// If `inter_byte_timeout` is set, this only applies until the header of the StatusPacket has been received.
+    pub fn timeout(mut self, timeout: hal::time::MilliSecond) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Time to wait for each byte of a StatusPacket once its header has been received, instead of `timeout`.
+    ///
+    /// The motor may take a while before answering but then sends the whole packet back-to-back, so a short inter-byte timeout detects truncated packets quickly while `timeout` stays generous.
+    pub fn inter_byte_timeout(mut self, timeout: hal::time::MilliSecond) -> Self {
+        self.inter_byte_timeout = Some(timeout);
+        self
+    }
+    /// Timeout used while scanning, instead of `timeout`.
+    ///
+    /// A short scan timeout makes the absent ids fail fast while the other instructions keep a generous timeout.
+    pub fn scan_timeout(mut self, timeout: hal::time::MilliSecond) -> Self {
+        self.scan_timeout = Some(timeout);
+        self
+    }
+    /// Baud rate of the bus, used to adapt the timeout to the length of the StatusPacket.
+    ///
+    /// When it is set, the time needed to transmit the remaining bytes of the StatusPacket is added to `timeout` which then only acts as a margin.
+    /// This avoids spurious timeouts on slow buses and waiting too long on fast ones.
+    pub fn baud_rate(mut self, baud: BaudRate) -> Self {
+        self.baud_rate = Some(baud);
+        self
+    }
+    /// Number of times an InstructionPacket is sent again when its StatusPacket times out (or is corrupted, see `retry_invalid_checksum`).
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+    /// Also send an InstructionPacket again when its StatusPacket has an invalid checksum (enabled by default).
+    ///
+    /// A corrupted StatusPacket is usually due to transient noise on the bus, so sending the instruction again almost always succeeds.
+    pub fn retry_invalid_checksum(mut self, enabled: bool) -> Self {
+        self.retry_invalid_checksum = enabled;
+        self
+    }
+    /// Status return level configured on the motors, used to know which instructions will be answered.
+    pub fn status_return_level(mut self, level: StatusReturnLevel) -> Self {
+        self.status_return_level = level;
+        self
+    }
+    /// Driver-enable pin of a half-duplex RS-485 transceiver.
+    ///
+    /// It is set high while sending an InstructionPacket and set low before waiting for the StatusPacket.
+    pub fn de_pin<P>(self, de: P) -> ControllerV2Builder<RX, TX, CLOCK, P>
+    where
+        P: hal::digital::OutputPin,
+    {
+        ControllerV2Builder {
+            rx: self.rx,
+            tx: self.tx,
+            clock: self.clock,
+            de: Some(de),
+            timeout: self.timeout,
+            inter_byte_timeout: self.inter_byte_timeout,
+            scan_timeout: self.scan_timeout,
+            baud_rate: self.baud_rate,
+            retries: self.retries,
+            retry_invalid_checksum: self.retry_invalid_checksum,
+            status_return_level: self.status_return_level,
+        }
+    }
+    /// Create the configured controller.
+    pub fn build(mut self) -> ControllerV2<RX, TX, CLOCK, DE> {
+        if let Some(ref mut de) = self.de {
+            de.set_low();
+        }
+
+        ControllerV2 {
+            protocol: PhantomData,
+            rx: self.rx,
+            tx: self.tx,
+            de: self.de,
+            clock: self.clock,
+            timeout: self.timeout,
+            inter_byte_timeout: self.inter_byte_timeout,
+            scan_timeout: self.scan_timeout,
+            baud_rate: self.baud_rate,
+            retries: self.retries,
+            retry_invalid_checksum: self.retry_invalid_checksum,
+            status_return_level: self.status_return_level,
+            status_return_levels: BTreeMap::new(),
+            echo_cancellation: false,
+            flush_before_transaction: false,
+            pending_echo: 0,
+            ignore_alert: false,
+            eeprom_guard: true,
+            eeprom_cache: None,
+            learn_status_return_level: false,
+            crc_check: true,
+            crc_mismatch: false,
+            timing_capture: false,
+            sent_at: None,
+            last_transaction_duration: None,
+            reception: None,
+            pending_read: None,
+            body_buffer: Vec::new(),
+            packet_buffer: Vec::new(),
+            #[cfg(feature = "std")]
+            packet_logger: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Instruction {
+    Ping = 0x01,
+    ReadData = 0x02,
+    WriteData = 0x03,
+    Reset = 0x06,
+    Clear = 0x10,
+    SyncRead = 0x82,
+    SyncWrite = 0x83,
+    FastSyncRead = 0x8A,
+}
+
+impl StatusReturnLevel {
+    fn answers(&self, instruction: u8) -> bool {
+        const READ_INSTRUCTIONS: [Instruction; 3] = [
+            Instruction::ReadData,
+            Instruction::SyncRead,
+            Instruction::FastSyncRead,
+        ];
+
+        if instruction == Instruction::Ping as u8 {
+            true
+        } else if READ_INSTRUCTIONS.iter().any(|&i| i as u8 == instruction) {
+            *self != StatusReturnLevel::PingOnly
+        } else {
+            *self == StatusReturnLevel::All
+        }
+    }
+}
+
+/// Motor ids are in 0-252 (253 is reserved)
+const MAX_ID: u8 = 253;
+/// Start of all packets
+const HEADER: [u8; 4] = [0xFF, 0xFF, 0xFD, 0x00];
+/// Length of a StatusPacket without any parameter
+const MIN_STATUS_PACKET_LENGTH: usize = 11;
+/// Number of sync bytes at the start of the header (the last header byte is reserved)
+const SYNC_LENGTH: usize = 3;
+/// Instruction field of all StatusPackets
+const STATUS_INSTRUCTION: u8 = 0x55;
+
+/// Packet header are constructed as follows [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`]
+#[derive(Debug)]
+struct PacketHeader {
+    _id: u8,
+    length: u16,
+}
+impl PacketHeader {
+    fn from_bytes(bytes: &[u8]) -> Result<PacketHeader, DynamixelError> {
+        if bytes.len() < PacketHeader::length() {
+            return Err(DynamixelError::parsing_error());
+        }
+        if bytes[..SYNC_LENGTH] != HEADER[..SYNC_LENGTH] {
+            return Err(DynamixelError::parsing_error());
+        }
+
+        Ok(PacketHeader {
+            _id: bytes[4],
+            length: pack!(bytes[5], bytes[6]),
+        })
+    }
+    const fn length() -> usize {
+        7
+    }
+}
+
+#[derive(Debug)]
+struct InstructionPacket {
+    id: u8,
+    instruction: u8,
+    parameters: Vec<u8>,
+}
+impl InstructionPacket {
+    fn new(id: u8, instruction: Instruction, parameters: Vec<u8>) -> InstructionPacket {
+        InstructionPacket::custom(id, instruction as u8, parameters)
+    }
+    fn custom(id: u8, instruction: u8, parameters: Vec<u8>) -> InstructionPacket {
+        InstructionPacket {
+            id,
+            instruction,
+            parameters,
+        }
+    }
+    fn factory_reset(id: u8, scope: ResetScope) -> InstructionPacket {
+        InstructionPacket::new(id, Instruction::Reset, vec![scope as u8])
+    }
+    fn clear_multi_turn(id: u8) -> InstructionPacket {
+        // The clear instruction only accepts this fixed parameter sequence.
+        const CLEAR_MULTI_TURN: [u8; 5] = [0x01, 0x44, 0x58, 0x4C, 0x22];
+
+        InstructionPacket::new(id, Instruction::Clear, CLEAR_MULTI_TURN.to_vec())
+    }
+    fn sync_read_data(ids: &[u8], addr: u16, len: u16) -> InstructionPacket {
+        let (addr_l, addr_h) = unpack!(addr);
+        let (len_l, len_h) = unpack!(len);
+
+        let mut param = vec![addr_l, addr_h, len_l, len_h];
+        param.extend(ids);
+
+        InstructionPacket::new(BROADCAST_ID, Instruction::SyncRead, param)
+    }
+    fn fast_sync_read_data(ids: &[u8], addr: u16, len: u16) -> InstructionPacket {
+        let (addr_l, addr_h) = unpack!(addr);
+        let (len_l, len_h) = unpack!(len);
+
+        let mut param = vec![addr_l, addr_h, len_l, len_h];
+        param.extend(ids);
+
+        InstructionPacket::new(BROADCAST_ID, Instruction::FastSyncRead, param)
+    }
+    #[cfg(test)]
+    fn write_bytes(id: u8, addr: u16, data: &[u8]) -> InstructionPacket {
+        InstructionPacket::with_address::<V2>(id, Instruction::WriteData, addr, data)
+    }
+    /// Instruction whose parameters are an address (encoded as in the protocol `P`) followed by `data`.
+    fn with_address<P>(
+        id: u8,
+        instruction: Instruction,
+        addr: u16,
+        data: &[u8],
+    ) -> InstructionPacket
+    where
+        P: Protocol,
+    {
+        let mut parameters = vec![0; P::ADDRESS_LENGTH];
+        address_into::<P>(addr, &mut parameters);
+        parameters.extend(data);
+
+        InstructionPacket::new(id, instruction, parameters)
+    }
+    /// [0xFF, 0xFF, 0xFD, 0x00, ID, LEN_L, LEN_H, INST, PARAM 1, PARAM 2, ..., PARAM N, CRC_L, CRC_H]
+    ///
+    /// The instruction and parameters are stuffed, the length counts the stuffed bytes and the CRC.
+    fn as_bytes(&self) -> Result<Vec<u8>, DynamixelError> {
+        self.encode::<V2>()
+    }
+    /// The whole packet, framed by the protocol `P`.
+    fn encode<P>(&self) -> Result<Vec<u8>, DynamixelError>
+    where
+        P: Protocol,
+    {
+        let mut body = vec![self.instruction];
+        body.extend(&self.parameters);
+
+        let mut buff = Vec::new();
+        frame::<P>(self.id, &body, &mut buff)?;
+
+        Ok(buff)
+    }
+}
+
+/// Status Packet are constructed as follows:
+/// [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`, 0x55, ERROR, PARAM 1, PARAM 2, ..., PARAM N, `CRC_L`, `CRC_H`]
+impl StatusPacket {
+    #[cfg(test)]
+    fn from_bytes(bytes: &[u8]) -> Result<StatusPacket, DynamixelError> {
+        Ok(StatusPacket::parse(bytes, true)?.0)
+    }
+    /// Parse a whole StatusPacket, a malformed one (e.g. truncated by a noisy bus) is reported as an error instead of panicking.
+    ///
+    /// The packet is also returned when its CRC does not match if `check_crc` is not set, along with whether the CRC was valid.
+    fn parse(bytes: &[u8], check_crc: bool) -> Result<(StatusPacket, bool), DynamixelError> {
+        let end = bytes.len();
+        if end < MIN_STATUS_PACKET_LENGTH {
+            return Err(DynamixelError::parsing_error());
+        }
+        let header = PacketHeader::from_bytes(&bytes[..PacketHeader::length()])?;
+        if PacketHeader::length() + header.length as usize != end {
+            return Err(DynamixelError::parsing_error());
+        }
+        // An instruction packet (e.g. the echo of the one sent) is not a StatusPacket, whatever its CRC.
+        if bytes[PacketHeader::length()] != STATUS_INSTRUCTION {
+            return Err(DynamixelError::parsing_error());
+        }
+        let valid_crc = crc(&bytes[..end - 2]) == pack!(bytes[end - 2], bytes[end - 1]);
+        if check_crc && !valid_crc {
+            return Err(DynamixelError::invalid_checksum());
+        }
+
+        let id = bytes[4];
+        let _length = header.length;
+        let body = unstuff(&bytes[7..end - 2]);
+        if body.len() < 2 {
+            return Err(DynamixelError::parsing_error());
+        }
+        let error_code = if body[1] == 0 { None } else { Some(body[1]) };
+        let parameters = body[2..].to_vec();
+        let status = StatusPacket {
+            id,
+            _length,
+            error_code,
+            parameters,
+        };
+
+        Ok((status, valid_crc))
+    }
+    /// Fast sync read answers are the concatenation of one segment per motor: [ERROR, ID, DATA 1, ..., DATA N, `CRC_L`, `CRC_H`]
+    ///
+    /// The error of the first segment is parsed as the packet error and the CRC of the last segment is the packet CRC.
+    fn fast_sync_read_segments(&self, len: u16) -> Result<Vec<(u8, u8, u32)>, DynamixelError> {
+        let segment_length = len as usize + 4;
+
+        let mut bytes = vec![self.error_code.unwrap_or(0)];
+        bytes.extend(&self.parameters);
+        // Placeholder for the last segment CRC, already checked and stripped as the packet CRC.
+        bytes.extend(&[0, 0]);
+
+        if bytes.len() % segment_length != 0 {
+            return Err(DynamixelError::parsing_error());
+        }
+
+        bytes
+            .chunks(segment_length)
+            .map(|s| Ok((s[1], s[0], dxl_decode_data!(len, s[2..])?)))
+            .collect()
+    }
+    /// The error is made of the alert bit (bit 7) and an error number (bits 0-6).
+    ///
+    /// Only the error number means the instruction failed, the alert bit is just reported unless `ignore_alert` is set.
+    fn checked(self, ignore_alert: bool) -> Result<StatusPacket, DynamixelError> {
+        match self.error_code {
+            Some(e) if e & !ALERT_BIT == INSTRUCTION_ERROR => {
+                Err(DynamixelError::instruction_error())
+            }
+            Some(e) if e & !ALERT_BIT != 0 => Err(DynamixelError::status_error_code(e)),
+            Some(_) if !ignore_alert => Err(DynamixelError::alert()),
+            _ => Ok(self),
+        }
+    }
+    #[cfg(test)]
+    /// [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`, 0x55, ERROR, PARAM 1, PARAM 2, ..., PARAM N, `CRC_L`, `CRC_H`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let (len_l, len_h) = unpack!(self._length);
+        let mut bytes = vec![
+            0xFF,
+            0xFF,
+            0xFD,
+            0x00,
+            self.id,
             len_l,
             len_h,
-            self.instruction as u8,
+            STATUS_INSTRUCTION,
+            self.error_code.unwrap_or(0),
         ];
+        bytes.extend(&self.parameters);
+        let crc = crc(&bytes);
+        let (crc_l, crc_h) = unpack!(crc);
+        bytes.extend(vec![crc_l, crc_h]);
+        bytes
+    }
+}
+
+/// Byte stuffing: a 0xFD is inserted after each [0xFF, 0xFF, 0xFD] sequence so it can not be mistaken for a header.
+#[cfg(test)]
+fn stuff(bytes: &[u8]) -> Vec<u8> {
+    let mut stuffed = Vec::with_capacity(bytes.len());
+
+    for &b in bytes {
+        stuffed.push(b);
+        if stuffed.ends_with(&[0xFF, 0xFF, 0xFD]) {
+            stuffed.push(0xFD);
+        }
+    }
+
+    stuffed
+}
+
+/// Maximum length of an InstructionPacket with `parameters` bytes of parameters, worst case byte stuffing included.
+///
+/// Being a `const fn`, it can size a stack buffer at compile time (e.g. `[0; max_packet_size(6)]`).
+pub const fn max_packet_size(parameters: usize) -> usize {
+    // Header, then the instruction and the parameters with one stuffing byte every 3 bytes, then the CRC
+    PacketHeader::length() + (parameters + 1) + (parameters + 1) / 3 + 2
+}
+
+/// Maximum length of any packet of the protocol 2.0.
+///
+/// The length field of the header is a `u16` counting the instruction (or error), the parameters, the stuffing bytes and the CRC.
+pub const MAX_PACKET_SIZE: usize = PacketHeader::length() + 0xFFFF;
+
+/// Maximum length of a Ping, ReadData or WriteData (of up to 4 bytes) InstructionPacket, byte stuffing included.
+///
+/// A buffer of this size is always large enough for `encode_ping`, `encode_read_data` and `encode_write_data`.
+pub const MAX_REGISTER_PACKET_LENGTH: usize = max_packet_size(6);
+
+/// Encode a Ping InstructionPacket for motor `id` into `buff` without allocating.
+///
+/// It returns the length of the packet, or an invalid value error if `buff` is too small.
+pub fn encode_ping(id: u8, buff: &mut [u8]) -> Result<usize, DynamixelError> {
+    V2::frame_into(id, &[Instruction::Ping as u8], buff)
+}
+/// Encode a ReadData InstructionPacket of `len` bytes at `addr` on motor `id` into `buff` without allocating.
+///
+/// It returns the length of the packet, or an invalid value error if `buff` is too small.
+pub fn encode_read_data(
+    id: u8,
+    addr: u16,
+    len: u16,
+    buff: &mut [u8],
+) -> Result<usize, DynamixelError> {
+    encode_read::<V2>(id, addr, len, buff)
+}
+/// Encode a WriteData InstructionPacket of `data` to the `len` bytes register at `addr` on motor `id` into `buff` without allocating.
+///
+/// It returns the length of the packet, or an invalid value error if `buff` is too small.
+pub fn encode_write_data(
+    id: u8,
+    addr: u16,
+    len: u16,
+    data: u32,
+    buff: &mut [u8],
+) -> Result<usize, DynamixelError> {
+    encode_write::<V2>(id, addr, len, data, buff)
+}
+/// Encode a SyncWrite InstructionPacket of `data` (`(id, value)` pairs) to the register `REG`, without sending it.
+///
+/// This is meant to queue or record the bus traffic, e.g. to send the packet later from a custom transmitter.
+/// An invalid value error is returned if a value does not fit in the register length.
+pub fn encode_sync_write_data<REG>(reg: &REG, data: &[(u8, u32)]) -> Result<Vec<u8>, DynamixelError>
+where
+    REG: Register + ?Sized,
+{
+    let (addr_l, addr_h) = unpack!(reg.address());
+    let (len_l, len_h) = unpack!(reg.length());
+
+    let mut body = Vec::new();
+    sync_write_body(
+        &[addr_l, addr_h, len_l, len_h],
+        reg.length(),
+        data.iter().cloned(),
+        &mut body,
+    )?;
+
+    let mut packet = Vec::new();
+    frame::<V2>(BROADCAST_ID, &body, &mut packet)?;
+
+    Ok(packet)
+}
+
+/// Same as `encode_read_data` with the addresses of the protocol `P`.
+fn encode_read<P>(id: u8, addr: u16, len: u16, buff: &mut [u8]) -> Result<usize, DynamixelError>
+where
+    P: Protocol,
+{
+    let mut body = [Instruction::ReadData as u8, 0, 0, 0, 0];
+    let length = address_into::<P>(addr, &mut body[1..]);
+    let length = length + address_into::<P>(len, &mut body[1 + length..]);
+
+    P::frame_into(id, &body[..1 + length], buff)
+}
+/// Same as `encode_write_data` with the addresses of the protocol `P`.
+fn encode_write<P>(
+    id: u8,
+    addr: u16,
+    len: u16,
+    data: u32,
+    buff: &mut [u8],
+) -> Result<usize, DynamixelError>
+where
+    P: Protocol,
+{
+    let mut body = [Instruction::WriteData as u8, 0, 0, 0, 0, 0, 0];
+    let start = 1 + address_into::<P>(addr, &mut body[1..]);
+    let length = dxl_code_data!(len, data, &mut body[start..])?;
+
+    P::frame_into(id, &body[..start + length], buff)
+}
+/// Write `value` (little endian) on the `ADDRESS_LENGTH` first bytes of `buff` and return this length.
+fn address_into<P>(value: u16, buff: &mut [u8]) -> usize
+where
+    P: Protocol,
+{
+    for (i, b) in buff[..P::ADDRESS_LENGTH].iter_mut().enumerate() {
+        *b = (value >> (8 * i)) as u8;
+    }
+
+    P::ADDRESS_LENGTH
+}
+
+/// Write into `body` the SyncWrite body ([INST, `prefix`, ID 1, DATA 1, ..., ID N, DATA N]) of `data` to the `len` bytes register.
+fn sync_write_body<I>(
+    prefix: &[u8],
+    len: u16,
+    data: I,
+    body: &mut Vec<u8>,
+) -> Result<(), DynamixelError>
+where
+    I: Iterator<Item = (u8, u32)>,
+{
+    body.clear();
+    body.push(Instruction::SyncWrite as u8);
+    body.extend(prefix);
+    for (id, data) in data {
+        if len < 4 && data >> (8 * len) != 0 {
+            return Err(DynamixelError::invalid_value());
+        }
+        body.push(id);
+
+        let start = body.len();
+        body.resize(start + 4, 0);
+        let length = dxl_code_data!(len, data, &mut body[start..])?;
+        body.truncate(start + length);
+    }
+
+    Ok(())
+}
+
+/// Write into `buff` the whole packet for motor `id` containing `body` ([INST, PARAM 1, ..., PARAM N]).
+///
+/// The body is stuffed, the length counts the stuffed bytes and the CRC.
+fn frame_into(id: u8, body: &[u8], buff: &mut [u8]) -> Result<usize, DynamixelError> {
+    let start = PacketHeader::length();
+    if buff.len() < start {
+        return Err(DynamixelError::invalid_value());
+    }
+    buff[..HEADER.len()].copy_from_slice(&HEADER);
+    buff[HEADER.len()] = id;
+
+    let mut length = start;
+    for &b in body {
+        push_into(buff, &mut length, b)?;
+        if buff[start..length].ends_with(&[0xFF, 0xFF, 0xFD]) {
+            push_into(buff, &mut length, 0xFD)?;
+        }
+    }
+
+    let (len_l, len_h) = unpack!(length - start + 2);
+    buff[5] = len_l;
+    buff[6] = len_h;
+
+    let (crc_l, crc_h) = unpack!(crc(&buff[..length]));
+    push_into(buff, &mut length, crc_l)?;
+    push_into(buff, &mut length, crc_h)?;
+
+    Ok(length)
+}
+fn push_into(buff: &mut [u8], length: &mut usize, b: u8) -> Result<(), DynamixelError> {
+    let slot = buff
+        .get_mut(*length)
+        .ok_or_else(DynamixelError::invalid_value)?;
+    *slot = b;
+    *length += 1;
+
+    Ok(())
+}
+/// Same as `Protocol::frame_into` in a `Vec` large enough for the longest packet (e.g. the worst case stuffing of the protocol v2).
+fn frame<P>(id: u8, body: &[u8], buff: &mut Vec<u8>) -> Result<(), DynamixelError>
+where
+    P: Protocol,
+{
+    buff.clear();
+    buff.resize(P::max_packet_length(body.len()), 0);
+
+    let length = P::frame_into(id, body, buff)?;
+    buff.truncate(length);
+
+    Ok(())
+}
+
+/// Remove the 0xFD inserted after each [0xFF, 0xFF, 0xFD] sequence by the byte stuffing.
+fn unstuff(bytes: &[u8]) -> Vec<u8> {
+    let mut unstuffed = Vec::with_capacity(bytes.len());
+    let mut stuffing = false;
+
+    for &b in bytes {
+        if stuffing && b == 0xFD {
+            stuffing = false;
+            continue;
+        }
+        unstuffed.push(b);
+        stuffing = unstuffed.ends_with(&[0xFF, 0xFF, 0xFD]);
+    }
+
+    unstuffed
+}
+
+#[cfg(feature = "crc16")]
+fn crc(bytes: &[u8]) -> u16 {
+    crc16::State::<crc16::BUYPASS>::calculate(bytes)
+}
+#[cfg(not(feature = "crc16"))]
+fn crc(bytes: &[u8]) -> u16 {
+    crc16_buypass(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate rand;
+    extern crate std;
+    extern crate test;
+
+    use self::rand::distributions::{Range, Sample};
+    use self::rand::random;
+    use self::std::cell::RefCell;
+    use self::std::rc::Rc;
+    use super::*;
+    use mock::{MockClock, MockSerial};
+    use nb;
+    use protocol::SingleId;
+
+    #[test]
+    fn parse_status_packet() {
+        let bytes = [0xFF, 0xFF, 0xFD, 0x00, 42, 6, 0, 0x55, 0, 0, 23, 4, 242];
+        let sp = StatusPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(sp.id, 42, "check id");
+        assert_eq!(sp._length, pack!(6_u8, 0_u8), "check length");
+        assert_eq!(sp.parameters, vec![0, 23], "check parameters");
+    }
+    #[test]
+    fn parse_random_status_packet() {
+        let mut rp = random_status_packet();
+        rp.error_code = None;
+        let bytes = rp.to_bytes();
+
+        let sp = StatusPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(sp.id, rp.id, "check id");
+        assert_eq!(sp._length, rp._length, "check length");
+        assert!(sp.error_code.is_none(), "check error code");
+        assert_eq!(sp.parameters, rp.parameters, "check parameters");
+    }
+    #[test]
+    fn status_error() {
+        let error: u8 = random();
+        let error = if error == 0 { 1 } else { error };
+        let mut bytes = vec![0xFF, 0xFF, 0xFD, 0x00, 42, 6, 0, 0x55, error, 0, 23];
+        let crc = crc(&bytes);
+        let (crc_l, crc_h) = unpack!(crc);
+        bytes.extend(vec![crc_l, crc_h]);
+        let sp = StatusPacket::from_bytes(&bytes).unwrap();
+
+        assert_eq!(sp.error_code, Some(error));
+    }
+    #[test]
+    fn instruction_error() {
+        let answer = StatusPacket {
+            id: 1,
+            _length: 4,
+            error_code: Some(ALERT_BIT | INSTRUCTION_ERROR),
+            parameters: vec![],
+        }
+        .to_bytes();
+        let mut c = fake_controller(&answer, false);
+
+        assert_eq!(
+            c.send_instruction(1, 0x42, &[]).unwrap_err().error,
+            ErrorType::InstructionError
+        );
+    }
+    #[test]
+    fn parse_packet_header() {
+        let header = PacketHeader::from_bytes(&[0xFF, 0xFF, 0xFD, 0x00, 1, 4, 0]).unwrap();
+        assert_eq!(header.length, 4);
+        assert!(PacketHeader::from_bytes(&[0xFF, 0xFF, 0xFD, 0x01, 1, 4, 0]).is_ok());
+
+        assert!(PacketHeader::from_bytes(&[0xFF, 0xFF, 0xFD, 0x00]).is_err());
+        assert!(PacketHeader::from_bytes(&[0xFF, 0xFE, 0xFD, 0x00, 1, 4, 0]).is_err());
+    }
+    #[test]
+    fn parse_short_status_packet() {
+        let bytes = status_bytes(1, vec![]);
+
+        for &len in &[0, 5, 8] {
+            assert_eq!(
+                StatusPacket::from_bytes(&bytes[..len]).unwrap_err().error,
+                ErrorType::Parsing
+            );
+        }
+        assert!(PacketHeader::from_bytes(&bytes[..5]).is_err());
+
+        let mut bytes = status_bytes(1, vec![1, 2]);
+        bytes[5] = 4;
+        assert_eq!(
+            StatusPacket::from_bytes(&bytes).unwrap_err().error,
+            ErrorType::Parsing
+        );
+    }
+    #[test]
+    fn status_instruction_marker() {
+        let mut bytes = status_bytes(1, vec![1, 2]);
+        bytes[7] = 0x02;
+        assert_eq!(
+            StatusPacket::from_bytes(&bytes).unwrap_err().error,
+            ErrorType::Parsing
+        );
+
+        let end = bytes.len() - 2;
+        let (crc_l, crc_h) = unpack!(crc(&bytes[..end]));
+        bytes[end] = crc_l;
+        bytes[end + 1] = crc_h;
+        assert_eq!(
+            StatusPacket::parse(&bytes, false).unwrap_err().error,
+            ErrorType::Parsing
+        );
+    }
+    #[test]
+    fn protocol_v2() {
+        let bytes = status_bytes(1, vec![0x20, 0x03]);
+        assert!(V2::is_header_start(&bytes[..2]));
+        assert!(!V2::is_header_start(&[0xFF, 0x00]));
+        assert_eq!(
+            V2::packet_length(&bytes[..V2::HEADER_LENGTH]).unwrap(),
+            bytes.len()
+        );
+
+        let (status, valid_crc) = V2::parse_status(&bytes, true).unwrap();
+        assert!(valid_crc);
+        assert_eq!(status.parameters(), &[0x20, 0x03]);
+
+        let mut buff = vec![0; V2::max_packet_length(5)];
+        let length = V2::frame_into(1, &[0x02, 0x84, 0, 4, 0], &mut buff).unwrap();
+        let packet = InstructionPacket::new(1, Instruction::ReadData, vec![0x84, 0, 4, 0]);
+        assert_eq!(&buff[..length], &packet.as_bytes().unwrap()[..]);
+    }
+    #[test]
+    fn max_packet_size_covers_stuffing() {
+        assert_eq!(MAX_REGISTER_PACKET_LENGTH, 18);
+
+        // Worst case stuffing: [INST, 0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0xFD]
+        let body = [0x03, 0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0xFD];
+        let mut buff = [0; max_packet_size(9)];
+        assert_eq!(frame_into(1, &body, &mut buff).unwrap(), buff.len());
+
+        assert_eq!(MAX_PACKET_SIZE, 7 + 65535);
+    }
+    #[test]
+    fn encode_without_alloc() {
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+
+        let length = encode_ping(1, &mut buff).unwrap();
+        assert_eq!(
+            &buff[..length],
+            &[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E]
+        );
+
+        let length = encode_read_data(1, 0x84, 4, &mut buff).unwrap();
+        let packet = InstructionPacket::new(1, Instruction::ReadData, vec![0x84, 0, 4, 0]);
+        assert_eq!(&buff[..length], &packet.as_bytes().unwrap()[..]);
+
+        // Worst case stuffing: [INST, 0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0xFD]
+        let length = encode_write_data(1, 0xFFFF, 4, 0xFDFF_FFFD, &mut buff).unwrap();
+        assert_eq!(length, MAX_REGISTER_PACKET_LENGTH);
+
+        assert!(encode_ping(1, &mut buff[..9]).is_err());
+        assert!(encode_write_data(1, 0x74, 3, 0, &mut buff).is_err());
+    }
+    #[test]
+    fn clear_multi_turn_packet() {
+        let bytes = InstructionPacket::clear_multi_turn(1).as_bytes().unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xFF, 0xFF, 0xFD, 0x00, 1, 8, 0, 0x10, 0x01, 0x44, 0x58, 0x4C, 0x22, 0xB1, 0xDC]
+        );
+    }
+    #[test]
+    fn factory_reset_packet() {
+        let bytes = InstructionPacket::factory_reset(1, ResetScope::KeepId)
+            .as_bytes()
+            .unwrap();
+        assert_eq!(&bytes[5..9], &[4, 0, 0x06, 0x01]);
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        assert!(c.factory_reset(1, ResetScope::All).is_ok());
+        assert_eq!(c.tx.sent()[8], 0xFF);
+    }
+    #[test]
+    fn split_fast_sync_read() {
+        let sp = StatusPacket {
+            id: BROADCAST_ID,
+            _length: 13,
+            error_code: None,
+            parameters: vec![1, 0x10, 0x00, 0xAB, 0xCD, 0x20, 2, 0x20, 0x01],
+        };
+        assert_eq!(
+            sp.fast_sync_read_segments(2).unwrap(),
+            vec![(1, 0, 0x0010), (2, 0x20, 0x0120)]
+        );
+        assert!(sp.fast_sync_read_segments(1).is_err());
+    }
+    #[test]
+    fn byte_stuffing() {
+        let packet = InstructionPacket::custom(1, 0x03, vec![0xFF, 0xFF, 0xFD, 0x42]);
+        let bytes = packet.as_bytes().unwrap();
+        assert_eq!(bytes[5], 8, "length includes the stuffed byte");
+        assert_eq!(&bytes[7..13], &[0x03, 0xFF, 0xFF, 0xFD, 0xFD, 0x42]);
+
+        let parameters = vec![0xFF, 0xFF, 0xFD, 0xFF, 0xFF, 0xFD, 0xFD];
+        assert_eq!(unstuff(&stuff(&parameters)), parameters);
+    }
+    fn random_status_packet() -> StatusPacket {
+        let id: u8 = random();
+        let parameters = random_parameters();
+        let _length = (parameters.len() + 4) as u16;
+        let error_code = random_error();
+        StatusPacket {
+            id,
+            _length,
+            error_code,
+            parameters,
+        }
+    }
+    fn random_error() -> Option<u8> {
+        let e = Range::new(0u8, 8).sample(&mut rand::thread_rng());
+        match e {
+            0 => None,
+            e => Some(e),
+        }
+    }
+    fn random_parameters() -> Vec<u8> {
+        let size: u8 = random();
+        let mut data = Vec::new();
+        for _ in 0..size {
+            data.push(random());
+        }
+        data
+    }
+    fn fake_controller(
+        answer: &[u8],
+        echo: bool,
+    ) -> ControllerV2<MockSerial, MockSerial, MockClock> {
+        let serial = MockSerial::new();
+        serial.queue_answer(answer);
+        serial.set_echo(echo);
+        ControllerV2::new(serial.clone(), serial, MockClock::new())
+    }
+    fn fake_controller_answering(
+        answers: &[(u8, Vec<u8>)],
+    ) -> ControllerV2<MockSerial, MockSerial, MockClock> {
+        let mut answer = Vec::new();
+        for &(id, ref parameters) in answers {
+            answer.extend(status_bytes(id, parameters.clone()));
+        }
+        fake_controller(&answer, false)
+    }
+    fn write_data_bytes(id: u8, addr: u16, len: u16, data: u32) -> Vec<u8> {
+        let mut buff = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = encode_write_data(id, addr, len, data, &mut buff).unwrap();
+        buff[..length].to_vec()
+    }
+    fn status_bytes(id: u8, parameters: Vec<u8>) -> Vec<u8> {
+        StatusPacket {
+            id,
+            _length: (parameters.len() + 4) as u16,
+            error_code: None,
+            parameters,
+        }
+        .to_bytes()
+    }
+    #[test]
+    fn dump_registers() {
+        let table = (0..0x33).collect::<Vec<u8>>();
+        let mut c = fake_controller(&status_bytes(1, table), false);
+
+        let values = c.dump_registers(1, Model::XL320).unwrap();
+        assert_eq!(&c.tx.sent()[8..12], &[0x00, 0x00, 0x33, 0x00]);
+        assert_eq!(values.len(), ::motors::XL_320::REGISTERS.len());
+        assert_eq!(values[0], ("ModelNumber", 0x0100));
+        assert!(values.contains(&("ID", 0x03)));
+        assert!(values.contains(&("GoalPosition", 0x1F1E)));
+        assert!(values.contains(&("HardwareErrorStatus", 0x32)));
+
+        assert_eq!(
+            c.dump_registers(1, Model::Unknown(42)).unwrap_err().error,
+            ErrorType::UnsupportedRegister
+        );
+    }
+    #[test]
+    fn read_status_return_level() {
+        let mut c = fake_controller_answering(&[
+            (1, vec![0x24, 0x04]),
+            (1, vec![1]),
+            (1, vec![0x5E, 0x01]),
+            (1, vec![0]),
+        ]);
+
+        assert_eq!(c.read_status_return_level(1).unwrap(), 1);
+        assert_eq!(
+            &c.tx.sent()[c.tx.sent().len() - 6..c.tx.sent().len() - 2],
+            &[0x44, 0x00, 0x01, 0x00]
+        );
+        assert!(c.status_return_levels.is_empty());
+
+        c.set_status_return_level_learning(true);
+        assert_eq!(c.read_status_return_level(1).unwrap(), 0);
+        assert_eq!(
+            c.status_return_levels.get(&1),
+            Some(&StatusReturnLevel::PingOnly)
+        );
+
+        // The motor does not answer the write anymore
+        c.tx.clear_sent();
+        c.write_data(1, &::motors::XL_320::LED, 1).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x19, 1, 1));
+    }
+    #[test]
+    fn status_return_level() {
+        let answer = status_bytes(1, vec![]);
+
+        let mut c = fake_controller(&answer, false);
+        c.write_data(1, &::motors::XL_320::TorqueEnable, 1).unwrap();
+        assert_eq!(c.rx.unread(), 0);
+
+        let mut c = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .status_return_level(StatusReturnLevel::PingAndRead)
+            .build();
+        c.rx.queue_answer(&answer);
+        c.write_data(1, &::motors::XL_320::TorqueEnable, 1).unwrap();
+        assert_eq!(c.rx.unread(), answer.len());
+
+        c.set_expected_status_level(1, StatusReturnLevel::All);
+        c.write_data(1, &::motors::XL_320::TorqueEnable, 1).unwrap();
+        assert_eq!(c.rx.unread(), 0);
+    }
+    #[test]
+    fn non_blocking_read() {
+        let reg = ::motors::XL_320::PresentPosition;
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+
+        let mut c = fake_controller(&[], false);
+        match c.read_data_nb(1, &reg) {
+            Err(nb::Error::WouldBlock) => {}
+            _ => panic!("no answer received yet"),
+        }
+        c.rx.queue_answer(&answer[..5]);
+        match c.read_data_nb(1, &reg) {
+            Err(nb::Error::WouldBlock) => {}
+            _ => panic!("answer partially received"),
+        }
+        c.rx.queue_answer(&answer[5..]);
+        assert_eq!(c.read_data_nb(1, &reg).ok(), Some(0x0210));
+    }
+    #[test]
+    fn baud_rate_timeout() {
+        let reg = ::motors::XL_320::PresentPosition;
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+
+        let mut c = fake_controller(&answer[..5], false);
+        let mut polls = 0;
+        while let Err(nb::Error::WouldBlock) = c.read_data_nb(1, &reg) {
+            polls += 1;
+        }
+        assert_eq!(polls, 1, "times out after the fixed 1ms timeout");
+
+        let c = fake_controller(&answer[..5], false);
+        let mut c = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .baud_rate(BaudRate::B9600)
+            .build();
+        let mut polls = 0;
+        while let Err(nb::Error::WouldBlock) = c.read_data_nb(1, &reg) {
+            polls += 1;
+        }
+        assert!(polls > 1, "waits for the 7 remaining bytes at 9600 bps");
+
+        let c = fake_controller(&answer, false);
+        let mut c = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .baud_rate(BaudRate::B9600)
+            .build();
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+    }
+    #[test]
+    fn inter_byte_timeout() {
+        let reg = ::motors::XL_320::PresentPosition;
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+        let polls_until_timeout = |received: usize, inter_byte: bool| {
+            let c = fake_controller(&answer[..received], false);
+            let mut builder =
+                ControllerV2Builder::new(c.rx, c.tx, c.clock).timeout(hal::time::MilliSecond(20));
+            if inter_byte {
+                builder = builder.inter_byte_timeout(hal::time::MilliSecond(1));
+            }
+            let mut c = builder.build();
+
+            let mut polls = 0;
+            while let Err(nb::Error::WouldBlock) = c.read_data_nb(1, &reg) {
+                polls += 1;
+            }
+            polls
+        };
+
+        assert!(polls_until_timeout(9, false) > 1);
+        assert_eq!(polls_until_timeout(9, true), 1, "gap in the body");
+        assert!(polls_until_timeout(3, true) > 1, "header still awaited");
+    }
+    #[test]
+    fn retry_invalid_checksum() {
+        let reg = ::motors::XL_320::PresentPosition;
+        let mut answer = status_bytes(1, vec![0x10, 0x02]);
+        let last = answer.len() - 1;
+        answer[last] ^= 0xFF;
+        answer.extend(status_bytes(1, vec![0x10, 0x02]));
+
+        let c = fake_controller(&answer, false);
+        let mut c = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .retries(1)
+            .build();
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+        assert_eq!(c.tx.sent().len(), 2 * 14);
+
+        let c = fake_controller(&answer, false);
+        let mut c = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .retries(1)
+            .retry_invalid_checksum(false)
+            .build();
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::InvalidChecksum
+        );
+    }
+    #[test]
+    fn flush_input() {
+        let reg = ::motors::XL_320::PresentPosition;
+        let leftover = [0xFF, 0xFF, 0xFD];
+
+        let mut c = fake_controller(&leftover, false);
+        assert_eq!(c.flush_input(), 3);
+        assert!(c.rx.unread() == 0);
+
+        let mut c = fake_controller(&leftover, false);
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::Timeout { received: 3 }
+        );
+
+        let mut c = fake_controller(&leftover, false);
+        c.set_flush_before_transaction(true);
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::Timeout { received: 0 }
+        );
+    }
+    #[test]
+    fn answer_from_wrong_id() {
+        let answer = status_bytes(2, vec![0x10, 0x02]);
+        let mut c = fake_controller(&answer, false);
+
+        let e = c
+            .read_data(1, &::motors::XL_320::PresentPosition)
+            .unwrap_err();
+        assert_eq!(e.error, ErrorType::Parsing);
+    }
+    #[test]
+    fn resynchronization() {
+        let mut answer = vec![0x00, 0xFF, 0xFF, 0x13, 0xFF];
+        answer.extend(status_bytes(1, vec![0x10, 0x02]));
+        let mut c = fake_controller(&answer, false);
+        let reg = ::motors::XL_320::PresentPosition;
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+
+        let answer = vec![0x00; MAX_SKIPPED_BYTES + 1];
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_data(1, &reg).unwrap_err().error, ErrorType::Parsing);
+    }
+    #[test]
+    fn update_data() {
+        let mut c = fake_controller_answering(&[(1, vec![0b0101]), (1, vec![])]);
+
+        c.update_data(1, &::motors::XL_320::TorqueEnable, |v| v | 0b0010)
+            .unwrap();
+
+        let write = write_data_bytes(1, 0x18, 1, 0b0111);
+        assert!(c.tx.sent().ends_with(&write));
+    }
+    #[test]
+    fn write_data_verified() {
+        let reg = ::motors::XL_320::GoalPosition;
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![0x00, 0x02])]);
+        assert!(c.write_data_verified(1, &reg, 0x0200).is_ok());
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![0x10, 0x02])]);
+        assert_eq!(
+            c.write_data_verified(1, &reg, 0x0200).unwrap_err().error,
+            ErrorType::VerificationFailed
+        );
+    }
+    #[test]
+    fn set_id() {
+        use motors::XL_320::XL320;
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.set_id::<XL320>(BROADCAST_ID, 2).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert_eq!(
+            c.set_id::<XL320>(1, 253).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert_eq!(
+            c.set_id::<XL320>(1, BROADCAST_ID).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty(), "nothing is written");
+
+        let mut c =
+            fake_controller_answering(&[(1, vec![]), (2, vec![]), (2, vec![0x5E, 0x01, 0x1D])]);
+        assert!(c.set_id::<XL320>(1, 2).is_ok());
+        let mut expected = write_data_bytes(1, 0x18, 1, 0);
+        expected.extend(write_data_bytes(1, 0x03, 1, 2));
+        assert!(c.tx.sent().starts_with(&expected), "torque disabled first");
+
+        let mut c =
+            fake_controller_answering(&[(1, vec![]), (1, vec![]), (2, vec![0x5E, 0x01, 0x1D])]);
+        assert!(c.set_id::<XL320>(1, 2).is_ok());
+
+        let mut answer = status_bytes(1, vec![]);
+        let mut corrupted = status_bytes(2, vec![]);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xFF;
+        answer.extend(corrupted);
+        answer.extend(status_bytes(2, vec![]));
+        answer.extend(status_bytes(2, vec![0x5E, 0x01, 0x1D]));
+        let c = fake_controller(&answer, false);
+        let mut c = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .retries(1)
+            .build();
+        assert!(c.set_id::<XL320>(1, 2).is_ok(), "corrupted ack retried");
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (5, vec![])]);
+        assert_eq!(
+            c.set_id::<XL320>(1, 2).unwrap_err().error,
+            ErrorType::Parsing
+        );
+
+        let refused = StatusPacket {
+            id: 1,
+            _length: 4,
+            error_code: Some(ACCESS_ERROR),
+            parameters: vec![],
+        }
+        .to_bytes();
+        let mut answer = status_bytes(1, vec![]);
+        answer.extend(refused);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.set_id::<XL320>(1, 2).unwrap_err().error,
+            ErrorType::TorqueMustBeDisabled
+        );
+    }
+    #[test]
+    fn torque_enable() {
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.enable_torque::<::motors::XL_430::XL430>(1).unwrap();
+
+        let write = write_data_bytes(1, 0x40, 1, 1);
+        assert_eq!(c.tx.sent(), write);
+    }
+    #[test]
+    fn read_bytes() {
+        let answer = status_bytes(1, vec![1, 2, 3, 4, 5, 6]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_bytes(1, 0x80, 6).unwrap(), vec![1, 2, 3, 4, 5, 6]);
+
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.read_bytes(1, 0x80, 8).unwrap_err().error,
+            ErrorType::Parsing
+        );
+    }
+    #[test]
+    fn indirect_addressing() {
+        use motors::XL_430::XL430;
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.map_indirect::<XL430>(1, 2, 0x85).unwrap();
+        let write = write_data_bytes(1, 170, 2, 0x85);
+        assert_eq!(c.tx.sent(), write);
+        assert_eq!(
+            c.map_indirect::<XL430>(1, 21, 0x84).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.write_indirect::<XL430>(1, &[1, 2]).unwrap();
+        assert_eq!(&c.tx.sent()[8..12], &[224, 0, 1, 2]);
+
+        let mut c = fake_controller(&status_bytes(1, vec![0x10, 0x20]), false);
+        assert_eq!(c.read_indirect::<XL430>(1, 2).unwrap(), vec![0x10, 0x20]);
+    }
+    #[test]
+    fn write_bytes() {
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.write_bytes(1, 0x1E, &[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(
+            c.tx.sent(),
+            InstructionPacket::write_bytes(1, 0x1E, &[1, 2, 3, 4, 5, 6])
+                .as_bytes()
+                .unwrap()
+        );
+        assert_eq!(&c.tx.sent()[5..14], &[11, 0, 0x03, 0x1E, 0x00, 1, 2, 3, 4]);
+
+        let answer = StatusPacket {
+            id: 1,
+            _length: 4,
+            error_code: Some(ACCESS_ERROR),
+            parameters: vec![],
+        }
+        .to_bytes();
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.write_bytes(1, 0x1E, &[1]).unwrap_err().error,
+            ErrorType::StatusError(ACCESS_ERROR)
+        );
+    }
+    #[test]
+    fn broadcast_write() {
+        let mut c = fake_controller(&[], false);
+        c.write_data(BROADCAST_ID, &::motors::XL_320::TorqueEnable, 0)
+            .unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(BROADCAST_ID, 0x18, 1, 0));
+        assert!(c.clock.elapsed() < 2, "does not wait for an answer");
+    }
+    #[test]
+    fn broadcast_read() {
+        assert_eq!(MotorId::from(BROADCAST_ID), MotorId::Broadcast);
+        assert_eq!(MotorId::from(3), MotorId::Single(SingleId::new(3).unwrap()));
+        assert_eq!(SingleId::new(BROADCAST_ID), None);
+        assert_eq!(u8::from(MotorId::Broadcast), BROADCAST_ID);
+        assert_eq!(MotorId::from(3).single().unwrap(), 3);
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.read_data(BROADCAST_ID, &::motors::XL_320::PresentPosition)
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+        assert_eq!(
+            c.read_bytes(MotorId::Broadcast, 0x25, 2).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty(), "nothing is sent");
+    }
+    #[test]
+    fn broadcast_instruction() {
+        let mut c = fake_controller(&status_bytes(3, vec![0x5E, 0x01, 0x26]), false);
+        assert_eq!(
+            c.send_instruction(MotorId::Broadcast, 0x01, &[]).unwrap(),
+            vec![0x5E, 0x01, 0x26]
+        );
+        assert_eq!(
+            c.tx.sent(),
+            InstructionPacket::custom(BROADCAST_ID, 0x01, vec![])
+                .as_bytes()
+                .unwrap()
+        );
+    }
+    #[test]
+    fn read_velocity_and_position() {
+        use motors::XL_430::XL430;
+
+        let answer = status_bytes(1, vec![0xF6, 0xFF, 0xFF, 0xFF, 0x00, 0x08, 0x00, 0x00]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.read_velocity_and_position::<XL430>(1).unwrap(),
+            (-10, 2048)
+        );
+        assert_eq!(&c.tx.sent()[8..12], &[0x80, 0x00, 0x08, 0x00]);
+    }
+    #[test]
+    fn custom_register() {
+        use motors::CustomRegister;
+
+        let reg = CustomRegister { addr: 0xA8, len: 2 };
+        let mut c = fake_controller_answering(&[(1, vec![0x34, 0x12]), (1, vec![])]);
+
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x1234);
+        assert_eq!(&c.tx.sent()[8..12], &[0xA8, 0x00, 0x02, 0x00]);
+        c.tx.clear_sent();
+        c.write_data(1, &reg, 0x4321).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0xA8, 2, 0x4321));
+    }
+    #[test]
+    fn read_registers() {
+        use motors::XL_320::{PresentLoad, PresentPosition, PresentSpeed};
+
+        let answer = status_bytes(1, vec![0x10, 0x02, 0x20, 0x00, 0x00, 0x06]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.read_registers(1, &[&PresentLoad, &PresentPosition, &PresentSpeed])
+                .unwrap(),
+            vec![0x0600, 0x0210, 0x0020]
+        );
+        assert_eq!(&c.tx.sent()[8..12], &[0x25, 0, 6, 0]);
+
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.read_block(1, &PresentPosition, 6).unwrap(),
+            vec![0x10, 0x02, 0x20, 0x00, 0x00, 0x06]
+        );
+    }
+    #[test]
+    fn temperature_and_voltage() {
+        use motors::XL_430::XL430;
+
+        let mut c = fake_controller_answering(&[(1, vec![42]), (1, vec![0x78, 0x00])]);
+
+        assert_eq!(c.read_temperature::<XL430>(1).unwrap(), 42);
+        assert!((c.read_voltage::<XL430>(1).unwrap() - 12.0).abs() < 1e-4);
+        assert_eq!(
+            &c.tx.sent()[c.tx.sent().len() - 6..c.tx.sent().len() - 2],
+            &[0x90, 0, 2, 0]
+        );
+    }
+    #[test]
+    fn read_health() {
+        use motors::{XL_320::XL320, XM_430::XM430};
+
+        let mut c = fake_controller(&status_bytes(1, vec![74, 38]), false);
+        let health = c.read_health::<XL320>(1).unwrap();
+        assert_eq!(&c.tx.sent()[8..12], &[0x2D, 0x00, 0x02, 0x00]);
+        assert!((health.voltage - 7.4).abs() < 1e-3);
+        assert_eq!(health.temperature, 38);
+
+        let mut c = fake_controller(&status_bytes(1, vec![120, 0, 45]), false);
+        let health = c.read_health::<XM430>(1).unwrap();
+        assert_eq!(&c.tx.sent()[8..12], &[0x90, 0x00, 0x03, 0x00]);
+        assert!((health.voltage - 12.0).abs() < 1e-3);
+        assert_eq!(health.temperature, 45);
+    }
+    #[test]
+    fn wait_until_stopped() {
+        use motors::XL_320::XL320;
+
+        let mut c = fake_controller_answering(&[(1, vec![1]), (1, vec![0])]);
+        let (timeout, interval) = (hal::time::MilliSecond(1000), hal::time::MilliSecond(10));
+        assert!(c.wait_until_stopped::<XL320>(1, timeout, interval).is_ok());
+        assert_eq!(c.rx.unread(), 0);
+
+        let mut answer = Vec::new();
+        for _ in 0..10 {
+            answer.extend(status_bytes(1, vec![1]));
+        }
+        let mut c = fake_controller(&answer, false);
+        let timeout = hal::time::MilliSecond(50);
+        assert_eq!(
+            c.wait_until_stopped::<XL320>(1, timeout, interval)
+                .unwrap_err()
+                .error,
+            ErrorType::StillMoving
+        );
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.wait_until_stopped::<XL320>(1, timeout, interval)
+                .unwrap_err()
+                .error,
+            ErrorType::Timeout { received: 0 }
+        );
+    }
+    #[test]
+    fn flush_after_send() {
+        let mut c = fake_controller(&[], false);
+        c.sync_write_data(&::motors::XL_320::GoalPosition, &[(1, 0x0200)])
+            .unwrap();
+
+        assert!(!c.tx.sent().is_empty());
+        assert!(c.tx.flushed(), "TX flushed after the last byte");
+    }
+    #[test]
+    fn transmission_error() {
+        /// Transmitter failing with `error` (or never accepting any byte) after `accepted` bytes
+        struct BrokenTx {
+            error: Option<SerialErrorKind>,
+            accepted: usize,
+        }
+        impl hal::serial::Write<u8> for BrokenTx {
+            type Error = SerialErrorKind;
+            fn write(&mut self, _b: u8) -> nb::Result<(), Self::Error> {
+                if self.accepted > 0 {
+                    self.accepted -= 1;
+                    return Ok(());
+                }
+                match self.error {
+                    Some(kind) => Err(nb::Error::Other(kind)),
+                    None => Err(nb::Error::WouldBlock),
+                }
+            }
+            fn flush(&mut self) -> nb::Result<(), Self::Error> {
+                Ok(())
+            }
+            fn complete(&self) -> nb::Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+
+        let rx = fake_controller(&status_bytes(1, vec![1]), false).rx;
+        let tx = BrokenTx {
+            error: Some(SerialErrorKind::Framing),
+            accepted: 0,
+        };
+        let mut c = ControllerV2::new(rx, tx, MockClock::new());
+        assert_eq!(
+            c.read_data(1, &::motors::XL_320::ID).unwrap_err().error,
+            ErrorType::Serial(SerialErrorKind::Framing)
+        );
+        let results = c.sync_read_results(&[1, 2], &::motors::XL_320::ID);
+        assert_eq!(
+            results[1].1.as_ref().unwrap_err().error,
+            ErrorType::Serial(SerialErrorKind::Framing)
+        );
+
+        // A wedged transmitter times out instead of blocking forever
+        let rx = fake_controller(&[], false).rx;
+        let tx = BrokenTx {
+            error: None,
+            accepted: 0,
+        };
+        let mut c = ControllerV2::new(rx, tx, MockClock::new());
+        assert_eq!(
+            c.write_data(1, &::motors::XL_320::LED, 1)
+                .unwrap_err()
+                .error,
+            ErrorType::Serial(SerialErrorKind::TxTimeout)
+        );
+
+        // The echo of the bytes sent before the failure is still expected
+        let rx = fake_controller(&[], false).rx;
+        let tx = BrokenTx {
+            error: Some(SerialErrorKind::Framing),
+            accepted: 3,
+        };
+        let mut c = ControllerV2::new(rx, tx, MockClock::new());
+        c.set_echo_cancellation(true);
+        assert!(c.write_data(1, &::motors::XL_320::LED, 1).is_err());
+        assert_eq!(c.pending_echo, 3);
+    }
+    #[test]
+    fn sync_read_4_bytes() {
+        let reg = ::motors::XL_430::PresentPosition;
+
+        let mut c = fake_controller_answering(&[
+            (1, vec![0x00, 0x08, 0x00, 0x00]),
+            (2, vec![0xFF, 0x0F, 0x00, 0x00]),
+            (3, vec![0x78, 0x56, 0x34, 0x12]),
+        ]);
+
+        assert_eq!(
+            c.sync_read_data(&[1, 2, 3], &reg),
+            vec![(1, 0x0800), (2, 0x0FFF), (3, 0x1234_5678)]
+        );
+    }
+    #[test]
+    fn sync_read_results() {
+        let reg = ::motors::XL_320::PresentPosition;
+
+        let mut answer = status_bytes(1, vec![0x10, 0x02]);
+        answer.extend(
+            StatusPacket {
+                id: 2,
+                _length: 6,
+                error_code: Some(0x01),
+                parameters: vec![0x00, 0x00],
+            }
+            .to_bytes(),
+        );
+        let mut c = fake_controller(&answer, false);
+
+        let results: Vec<(u8, Result<u32, ErrorType>)> = c
+            .sync_read_results(&[1, 2, 3], &reg)
+            .into_iter()
+            .map(|(id, result)| (id, result.map_err(|e| e.error)))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                (1, Ok(0x0210)),
+                (2, Err(ErrorType::StatusError(0x01))),
+                (3, Err(ErrorType::Timeout { received: 0 })),
+            ]
+        );
+    }
+    #[test]
+    fn sync_read_voltage() {
+        use motors::XL_430::XL430;
+
+        let mut c = fake_controller_answering(&[(1, vec![120, 0]), (2, vec![118, 0])]);
+
+        let voltages = c.sync_read_voltage::<XL430>(&[1, 2, 3]);
+        assert_eq!(voltages.len(), 2);
+        assert_eq!(voltages[0].0, 1);
+        assert!((voltages[0].1 - 12.0).abs() < 1e-3);
+        assert_eq!(voltages[1].0, 2);
+        assert!((voltages[1].1 - 11.8).abs() < 1e-3);
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn packet_logger() {
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+        let mut c = fake_controller(&answer, false);
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let logged = log.clone();
+        c.set_packet_logger(Box::new(move |packet: &LoggedPacket| {
+            logged.borrow_mut().push((
+                packet.direction,
+                packet.id,
+                packet.instruction,
+                packet.error,
+                packet.bytes.to_vec(),
+            ))
+        }));
+        c.read_data(1, &::motors::XL_320::PresentPosition).unwrap();
+
+        let log = log.borrow();
+        assert_eq!(log.len(), 2);
+        assert_eq!(
+            log[0],
+            (Direction::Sent, 1, Some(0x02), None, c.tx.sent().clone())
+        );
+        assert_eq!(log[1], (Direction::Received, 1, Some(0x55), None, answer));
+
+        let answer = StatusPacket {
+            id: 3,
+            _length: 4,
+            error_code: Some(ACCESS_ERROR),
+            parameters: vec![],
+        }
+        .to_bytes();
+        let mut c = fake_controller(&answer, false);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let logged = log.clone();
+        c.set_packet_logger(Box::new(move |packet: &LoggedPacket| {
+            logged.borrow_mut().push((packet.id, packet.error))
+        }));
+        assert!(c.write_data(3, &::motors::XL_320::LED, 1).is_err());
+        assert_eq!(*log.borrow(), vec![(3, None), (3, Some(ACCESS_ERROR))]);
+    }
+    #[test]
+    fn move_to_smoothly() {
+        use motors::{XL_320::XL320, XL_430::XL430};
+
+        let mut answer = status_bytes(1, vec![0x00, 0x01]);
+        for _ in 0..3 {
+            answer.extend(status_bytes(1, vec![]));
+        }
+        let mut c = fake_controller(&answer, false);
+        let delay = hal::time::MilliSecond(5);
+        c.move_to_smoothly::<XL320>(1, 0x0128, 0x10, delay).unwrap();
+
+        assert_eq!(c.rx.unread(), 0, "3 steps: 0x110, 0x120, 0x128");
+        let last = write_data_bytes(1, 0x1E, 2, 0x0128);
+        assert!(c.tx.sent().ends_with(&last));
+
+        // Extended position mode: from -4096 to 0 in 2 steps.
+        let mut c = fake_controller_answering(&[
+            (1, vec![0x00, 0xF0, 0xFF, 0xFF]),
+            (1, vec![]),
+            (1, vec![]),
+        ]);
+        c.move_to_smoothly::<XL430>(1, 0, 0x800, delay).unwrap();
+        let mut expected = write_data_bytes(1, 0x74, 4, 0xFFFF_F800);
+        expected.extend(write_data_bytes(1, 0x74, 4, 0));
+        assert!(c.tx.sent().ends_with(&expected));
+        assert_eq!(c.rx.unread(), 0);
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.move_to_smoothly::<XL320>(1, 0x0128, 0, delay)
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+    }
+    #[test]
+    fn disable_all_torque() {
+        use motors::{XL_320, XL_430};
+
+        let mut c = fake_controller_answering(&[
+            (1, vec![0x5E, 0x01]),
+            (2, vec![0x24, 0x04]),
+            (3, vec![0x5E, 0x01]),
+        ]);
+        assert!(c.disable_all_torque(&[1, 2, 3]).unwrap().is_empty());
+
+        let mut expected = fake_controller(&[], false);
+        expected
+            .sync_write_data(&XL_320::TorqueEnable, &[(1, 0), (3, 0)])
+            .unwrap();
+        expected
+            .sync_write_data(&XL_430::TorqueEnable, &[(2, 0)])
+            .unwrap();
+        let sent = c.tx.sent().len();
+        let expected = expected.tx.sent();
+        assert_eq!(&c.tx.sent()[sent - expected.len()..], &expected[..]);
 
-        buff.extend(&self.parameters);
+        // Motor 1 is of an unknown model and motor 2 does not answer, motor 3 is still disabled.
+        let mut c = fake_controller_answering(&[(1, vec![0x1E, 0x00]), (3, vec![0x5E, 0x01])]);
+        assert_eq!(c.disable_all_torque(&[1, 3, 2]).unwrap(), vec![1, 2]);
 
-        let (crc_l, crc_h) = unpack!(crc(&buff));
-        buff.push(crc_l);
-        buff.push(crc_h);
+        let mut expected = fake_controller(&[], false);
+        expected
+            .sync_write_data(&XL_320::TorqueEnable, &[(3, 0)])
+            .unwrap();
+        assert!(c.tx.sent().ends_with(&expected.tx.sent()));
+    }
+    #[test]
+    fn encode_sync_write() {
+        let reg = ::motors::XL_320::GoalPosition;
+        let data = [(1, 0x0100), (2, 0x0200)];
 
-        buff
+        let mut c = fake_controller(&[], false);
+        c.sync_write_data(&reg, &data).unwrap();
+        assert_eq!(encode_sync_write_data(&reg, &data).unwrap(), c.tx.sent());
+
+        assert_eq!(
+            encode_sync_write_data(&reg, &[(1, 0x10000)])
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
     }
-}
+    #[test]
+    fn sync_group() {
+        let reg = ::motors::XL_320::GoalPosition;
+        let mut c = fake_controller(&[], false);
+        let group = c.sync_group(&[1, 2], &reg).unwrap();
 
-/// Status Packet are constructed as follows:
-/// [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`, 0x55, ERROR, PARAM 1, PARAM 2, ..., PARAM N, `CRC_L`, `CRC_H`]
-#[derive(Debug)]
-struct StatusPacket {
-    _id: u8,
-    _length: u16,
-    error_code: Option<u8>,
-    parameters: Vec<u8>,
-}
-impl StatusPacket {
-    fn from_bytes(bytes: &[u8]) -> Result<StatusPacket, DynamixelError> {
-        let end = bytes.len();
-        if crc(&bytes[..end - 2]) != pack!(bytes[end - 2], bytes[end - 1]) {
-            return Err(DynamixelError::invalid_checksum());
+        group.write(&mut c, &[0x0100, 0x0200]).unwrap();
+        let sent = c.tx.sent();
+        c.tx.clear_sent();
+        c.sync_write_data(&reg, &[(1, 0x0100), (2, 0x0200)])
+            .unwrap();
+        assert_eq!(sent, c.tx.sent());
+        c.tx.clear_sent();
+        assert!(group.write(&mut c, &[0x0100]).is_err());
+
+        c.rx.queue_answer(&status_bytes(1, vec![0x00, 0x01]));
+        c.rx.queue_answer(&status_bytes(2, vec![0x00, 0x02]));
+        assert_eq!(group.read(&mut c), vec![(1, 0x0100), (2, 0x0200)]);
+        assert_eq!(
+            c.tx.sent(),
+            InstructionPacket::sync_read_data(&[1, 2], 0x1E, 2)
+                .as_bytes()
+                .unwrap()
+        );
+    }
+    #[test]
+    fn sync_write_packet() {
+        let mut c = fake_controller(&[], false);
+        c.sync_write_data(&::motors::XL_430::GoalPosition, &[(1, 0xFFFD), (2, 0x0800)])
+            .unwrap();
+
+        let params = vec![0x74, 0, 4, 0, 1, 0xFD, 0xFF, 0, 0, 2, 0, 0x08, 0, 0];
+        let packet = InstructionPacket::new(BROADCAST_ID, Instruction::SyncWrite, params);
+        assert_eq!(c.tx.sent(), packet.as_bytes().unwrap());
+
+        // The buffers are reused by the next call.
+        c.tx.clear_sent();
+        c.sync_write_data(&::motors::XL_430::GoalPosition, &[(1, 0xFFFD), (2, 0x0800)])
+            .unwrap();
+        assert_eq!(c.tx.sent(), packet.as_bytes().unwrap());
+    }
+    #[test]
+    fn sync_write_out_of_range() {
+        let mut c = fake_controller(&[], false);
+        let reg = ::motors::XL_320::GoalPosition;
+
+        assert_eq!(
+            c.sync_write_data(&reg, &[(1, 0x0200), (2, 0x1_0000)])
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
+
+        c.sync_write_data(&reg, &[(1, 0x0200), (2, 0xFFFF)])
+            .unwrap();
+        assert!(!c.tx.sent().is_empty());
+    }
+    #[bench]
+    fn bench_sync_write_12_motors(b: &mut test::Bencher) {
+        let reg = ::motors::XL_430::GoalPosition;
+        let data: Vec<(u8, u32)> = (1..13).map(|id| (id, 2048)).collect();
+        let mut c = fake_controller(&[], false);
+
+        b.iter(|| {
+            c.sync_write_data(&reg, &data).unwrap();
+            c.tx.clear_sent();
+        });
+    }
+    #[test]
+    fn echo_cancellation() {
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+        let reg = ::motors::XL_320::PresentPosition;
+
+        let mut c = fake_controller(&answer, true);
+        c.set_echo_cancellation(true);
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+
+        let mut c = fake_controller(&answer, true);
+        assert!(c.read_data(1, &reg).is_err());
+
+        let mut c = fake_controller(&[], false);
+        c.set_echo_cancellation(true);
+        assert!(c.read_data(1, &reg).is_err());
+        assert_eq!(c.pending_echo, 0);
+        c.tx.set_echo(true);
+        c.rx.queue_answer(&answer);
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+    }
+    #[test]
+    fn alert_bit() {
+        let status = |error_code| {
+            StatusPacket {
+                id: 1,
+                _length: 6,
+                error_code: Some(error_code),
+                parameters: vec![0x10, 0x02],
+            }
+            .to_bytes()
+        };
+        let reg = ::motors::XL_320::PresentPosition;
+
+        let mut c = fake_controller(&status(0x80), false);
+        assert_eq!(c.read_data(1, &reg).unwrap_err().error, ErrorType::Alert);
+
+        let mut c = fake_controller(&status(0x80), false);
+        c.set_ignore_alert(true);
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+
+        let mut c = fake_controller(&status(0x87), false);
+        c.set_ignore_alert(true);
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::StatusError(0x87)
+        );
+    }
+    #[test]
+    fn typed_registers() {
+        use motors::XL_430;
+
+        let answer = status_bytes(1, vec![0x0C, 0xFE]);
+        let mut c = fake_controller(&answer, false);
+        let load: i16 = c.read_typed(1, &XL_430::PresentLoad).unwrap();
+        assert_eq!(load, -500);
+        assert_eq!(&c.tx.sent()[8..12], &[0x7E, 0x00, 0x02, 0x00]);
+
+        let answer = status_bytes(1, vec![]);
+        let mut c = fake_controller(&answer, false);
+        c.write_typed(1, &XL_430::GoalPosition, 2048).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x74, 4, 2048));
+    }
+    #[test]
+    fn eeprom_guard() {
+        let answer = StatusPacket {
+            id: 1,
+            _length: 4,
+            error_code: Some(ACCESS_ERROR),
+            parameters: vec![],
         }
+        .to_bytes();
 
-        let _id = bytes[4];
-        let _length = pack!(bytes[5], bytes[6]);
-        let error_code = if bytes[8] == 0 { None } else { Some(bytes[8]) };
-        let parameters = bytes[9..end - 2].to_vec();
-        Ok(StatusPacket {
-            _id,
-            _length,
-            error_code,
-            parameters,
-        })
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.write_data(1, &::motors::XL_430::BaudRate, 3)
+                .unwrap_err()
+                .error,
+            ErrorType::TorqueMustBeDisabled
+        );
+
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.write_data(1, &::motors::XL_430::GoalPosition, 0)
+                .unwrap_err()
+                .error,
+            ErrorType::StatusError(ACCESS_ERROR)
+        );
+
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.write_data(1, &::motors::XL_430::ModelNumber, 0)
+                .unwrap_err()
+                .error,
+            ErrorType::StatusError(ACCESS_ERROR)
+        );
+
+        let mut c = fake_controller(&answer, false);
+        c.set_eeprom_guard(false);
+        assert_eq!(
+            c.write_data(1, &::motors::XL_430::BaudRate, 3)
+                .unwrap_err()
+                .error,
+            ErrorType::StatusError(ACCESS_ERROR)
+        );
     }
-    #[cfg(test)]
-    /// [0xFF, 0xFF, 0xFD, 0x00, ID, `LEN_L`, `LEN_H`, 0x55, ERROR, PARAM 1, PARAM 2, ..., PARAM N, `CRC_L`, `CRC_H`]
-    fn to_bytes(&self) -> Vec<u8> {
-        let (len_l, len_h) = unpack!(self._length);
-        let mut bytes = vec![
-            0xFF,
-            0xFF,
-            0xFD,
-            0x00,
-            self._id,
-            len_l,
-            len_h,
-            0x55,
-            self.error_code.unwrap_or(0),
-        ];
-        bytes.extend(&self.parameters);
-        let crc = crc(&bytes);
-        let (crc_l, crc_h) = unpack!(crc);
-        bytes.extend(vec![crc_l, crc_h]);
-        bytes
+    #[test]
+    fn read_current() {
+        use motors::XM_430::XM430;
+
+        let answer = status_bytes(1, vec![100, 0]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_current::<XM430>(1).unwrap(), 269);
+        assert_eq!(&c.tx.sent()[8..12], &[0x7E, 0x00, 0x02, 0x00]);
+
+        let answer = status_bytes(1, vec![0x9C, 0xFF]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_current::<XM430>(1).unwrap(), -269);
+
+        let mut c = fake_controller_answering(&[(1, vec![0xFF, 0x7F]), (1, vec![0x00, 0x80])]);
+        assert_eq!(c.read_current::<XM430>(1).unwrap(), 32_767);
+        assert_eq!(c.read_current::<XM430>(1).unwrap(), -32_768);
     }
-}
+    #[test]
+    fn write_goal_velocity() {
+        use motors::{XL_430::XL430, XM_430::XM430};
 
-fn crc(bytes: &[u8]) -> u16 {
-    crc16::State::<crc16::BUYPASS>::calculate(bytes)
-}
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.write_goal_velocity::<XL430>(1, -265).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x68, 4, -265i32 as u32));
 
-#[cfg(test)]
-mod test {
-    extern crate rand;
+        let mut c = fake_controller(&[], false);
+        for &velocity in &[168, -168, -2_147_483_648] {
+            assert_eq!(
+                c.write_goal_velocity::<XM430>(1, velocity)
+                    .unwrap_err()
+                    .error,
+                ErrorType::InvalidValue
+            );
+        }
+        assert!(c.tx.sent().is_empty());
+    }
+    #[test]
+    fn read_present_pwm() {
+        use motors::XL_430::XL430;
 
-    // use nb;
-    use self::rand::distributions::{Range, Sample};
-    use self::rand::random;
-    use super::*;
+        let mut c = fake_controller_answering(&[(1, vec![0x75, 0x03]), (1, vec![0x9C, 0xFF])]);
+        assert_eq!(c.read_present_pwm::<XL430>(1).unwrap(), 885);
+        assert_eq!(&c.tx.sent()[8..12], &[0x7C, 0x00, 0x02, 0x00]);
+        assert_eq!(c.read_present_pwm::<XL430>(1).unwrap(), -100);
+    }
+    #[test]
+    fn read_tick() {
+        use motors::XM_430::XM430;
 
+        let answer = status_bytes(1, vec![0xFF, 0x7F]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_tick::<XM430>(1).unwrap(), 32767);
+        assert_eq!(&c.tx.sent()[8..12], &[0x78, 0x00, 0x02, 0x00]);
+    }
     #[test]
-    fn parse_status_packet() {
-        let bytes = [0xFF, 0xFF, 0xFD, 0x00, 42, 6, 0, 0x55, 0, 0, 23, 4, 242];
-        let sp = StatusPacket::from_bytes(&bytes).unwrap();
-        assert_eq!(sp._id, 42, "check id");
-        assert_eq!(sp._length, pack!(6_u8, 0_u8), "check length");
-        assert_eq!(sp.parameters, vec![0, 23], "check parameters");
+    fn set_gains() {
+        use motors::XL_430::XL430;
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![])]);
+
+        c.set_position_gains::<XL430>(1, 800, 0, 0x1234).unwrap();
+        let packet = InstructionPacket::write_bytes(1, 0x50, &[0x34, 0x12, 0, 0, 0x20, 0x03]);
+        assert_eq!(c.tx.sent(), packet.as_bytes().unwrap());
+
+        c.tx.clear_sent();
+        c.set_feedforward_gains::<XL430>(1, 100, 2).unwrap();
+        let packet = InstructionPacket::write_bytes(1, 0x58, &[2, 0, 100, 0]);
+        assert_eq!(c.tx.sent(), packet.as_bytes().unwrap());
+
+        c.tx.clear_sent();
+        assert_eq!(
+            c.set_position_gains::<XL430>(1, 16384, 0, 0)
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
     }
     #[test]
-    fn parse_random_status_packet() {
-        let mut rp = random_status_packet();
-        rp.error_code = None;
-        let bytes = rp.to_bytes();
+    fn read_moving_status() {
+        use motors::XM_430::XM430;
 
-        let sp = StatusPacket::from_bytes(&bytes).unwrap();
-        assert_eq!(sp._id, rp._id, "check id");
-        assert_eq!(sp._length, rp._length, "check length");
-        assert!(sp.error_code.is_none(), "check error code");
-        assert_eq!(sp.parameters, rp.parameters, "check parameters");
+        let mut c = fake_controller_answering(&[(1, vec![0b0000_0010]), (1, vec![0b0011_0001])]);
+
+        let status = c.read_moving_status::<XM430>(1).unwrap();
+        assert_eq!(&c.tx.sent()[8..12], &[0x7B, 0x00, 0x01, 0x00]);
+        assert!(status.profile_ongoing && !status.in_position);
+
+        let status = c.read_moving_status::<XM430>(1).unwrap();
+        assert!(status.in_position && status.is_done());
     }
     #[test]
-    fn status_error() {
-        let error: u8 = random();
-        let error = if error == 0 { 1 } else { error };
-        let mut bytes = vec![0xFF, 0xFF, 0xFD, 0x00, 42, 6, 0, 0x55, error, 0, 23];
-        let crc = crc(&bytes);
-        let (crc_l, crc_h) = unpack!(crc);
-        bytes.extend(vec![crc_l, crc_h]);
-        let sp = StatusPacket::from_bytes(&bytes).unwrap();
+    fn set_return_delay() {
+        use motors::{XL_320::XL320, XL_430::XL430};
 
-        assert_eq!(sp.error_code, Some(error));
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.set_return_delay::<XL430>(1, 99).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x09, 1, 50));
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.set_return_delay::<XL320>(1, 508).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x05, 1, 254));
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.set_return_delay::<XL320>(1, 510).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
     }
-    fn random_status_packet() -> StatusPacket {
-        let _id: u8 = random();
-        let parameters = random_parameters();
-        let _length = (parameters.len() + 4) as u16;
-        let error_code = random_error();
-        StatusPacket {
-            _id,
-            _length,
-            error_code,
-            parameters,
+    #[test]
+    fn goal_current_and_pwm() {
+        use motors::{XL_430::XL430, XM_430::XM430};
+
+        let answer = status_bytes(1, vec![]);
+        let mut c = fake_controller(&answer, false);
+        c.write_goal_current::<XM430>(1, -269).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x66, 2, 0xFF9C));
+
+        let mut c = fake_controller(&answer, false);
+        c.write_goal_pwm::<XL430>(1, -885).unwrap();
+        assert_eq!(
+            c.tx.sent(),
+            write_data_bytes(1, 0x64, 2, (-885_i16) as u16 as u32)
+        );
+
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.write_goal_current::<XM430>(1, 4000).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert_eq!(
+            c.write_goal_pwm::<XL430>(1, 886).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
+    }
+    #[test]
+    fn set_operating_mode() {
+        use motors::{OperatingMode, XL_430::XL430};
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![])]);
+        c.set_operating_mode::<XL430>(1, OperatingMode::Pwm)
+            .unwrap();
+        let mut expected = write_data_bytes(1, 0x40, 1, 0);
+        expected.extend(write_data_bytes(1, 0x0B, 1, 16));
+        assert_eq!(c.tx.sent(), expected);
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.set_operating_mode::<XL430>(1, OperatingMode::Current)
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
+    }
+    #[test]
+    fn set_position_limits() {
+        use motors::{XL_320::XL320, XL_430::XL430};
+
+        let answers = [(1, vec![]), (1, vec![]), (1, vec![])];
+        let mut c = fake_controller_answering(&answers);
+        c.set_position_limits::<XL430>(1, 1024, 3072).unwrap();
+        let mut expected = write_data_bytes(1, 0x40, 1, 0);
+        expected.extend(write_data_bytes(1, 0x34, 4, 1024));
+        expected.extend(write_data_bytes(1, 0x30, 4, 3072));
+        assert_eq!(c.tx.sent(), expected);
+
+        let mut c = fake_controller_answering(&answers);
+        c.set_position_limits::<XL320>(1, 200, 800).unwrap();
+        let mut expected = write_data_bytes(1, 0x18, 1, 0);
+        expected.extend(write_data_bytes(1, 0x06, 2, 200));
+        expected.extend(write_data_bytes(1, 0x08, 2, 800));
+        assert_eq!(c.tx.sent(), expected);
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.set_position_limits::<XL430>(1, 2048, 2048)
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
+    }
+    #[test]
+    fn velocity_profile() {
+        use motors::XL_430::XL430;
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![])]);
+        c.set_profile_velocity::<XL430>(1, 100).unwrap();
+        c.set_profile_acceleration::<XL430>(1, 20).unwrap();
+        let mut expected = write_data_bytes(1, 0x70, 4, 100);
+        expected.extend(write_data_bytes(1, 0x6C, 4, 20));
+        assert_eq!(c.tx.sent(), expected);
+    }
+    #[test]
+    fn timeout_received_bytes() {
+        let reg = ::motors::XL_320::PresentPosition;
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::Timeout { received: 0 }
+        );
+
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+        let mut c = fake_controller(&answer[..9], false);
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::Timeout { received: 9 }
+        );
+    }
+    #[test]
+    fn signed_position() {
+        use motors::XL_430::XL430;
+
+        let answer = status_bytes(1, vec![0x00, 0xF0, 0xFF, 0xFF]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_present_position_signed::<XL430>(1).unwrap(), -4096);
+
+        let answer = status_bytes(1, vec![0x00, 0x10, 0x00, 0x00]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_present_position_signed::<XL430>(1).unwrap(), 4096);
+
+        let answer = status_bytes(1, vec![0x00, 0xF0, 0xFF, 0xFF]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(c.read_present_position_degrees::<XL430>(1).unwrap(), -360.0);
+    }
+    #[test]
+    fn timing_capture() {
+        let answer = status_bytes(1, vec![0x10, 0x02]);
+        let reg = ::motors::XL_320::PresentPosition;
+
+        let mut c = fake_controller(&answer, false);
+        c.read_data(1, &reg).unwrap();
+        assert!(c.last_transaction_duration().is_none());
+
+        let mut c = fake_controller(&answer, false);
+        c.set_timing_capture(true);
+        c.read_data(1, &reg).unwrap();
+        let duration = c.last_transaction_duration().unwrap();
+        assert!(duration.0 > 0);
+
+        assert!(c.read_data(1, &reg).is_err());
+        assert_eq!(c.last_transaction_duration().unwrap().0, duration.0);
+    }
+    #[test]
+    fn disabled_crc_check() {
+        let mut answer = status_bytes(1, vec![0x10, 0x02]);
+        let end = answer.len();
+        answer[end - 1] ^= 0xFF;
+        let reg = ::motors::XL_320::PresentPosition;
+
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(
+            c.read_data(1, &reg).unwrap_err().error,
+            ErrorType::InvalidChecksum
+        );
+
+        let mut c = fake_controller(&answer, false);
+        c.set_crc_check(false);
+        assert_eq!(c.read_data(1, &reg).unwrap(), 0x0210);
+        assert!(c.crc_mismatch());
+    }
+    #[test]
+    fn resync() {
+        let mut c = fake_controller(&status_bytes(1, vec![0x06, 0x04, 0x26]), false);
+        c.resync(1).unwrap();
+        assert_eq!(c.timeout.0, TIMEOUT.0);
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(
+            c.resync(1).unwrap_err().error,
+            ErrorType::Timeout { received: 0 }
+        );
+        assert_eq!(c.timeout.0, TIMEOUT.0);
+    }
+    #[test]
+    fn scan_timeout() {
+        let c = fake_controller(&[], false);
+        let mut fast = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .timeout(hal::time::MilliSecond(50))
+            .scan_timeout(hal::time::MilliSecond(1))
+            .build();
+        assert!(fast.scan(1..4).unwrap().is_empty());
+        assert_eq!(fast.timeout.0, 50);
+
+        let c = fake_controller(&[], false);
+        let mut slow = ControllerV2Builder::new(c.rx, c.tx, c.clock)
+            .timeout(hal::time::MilliSecond(50))
+            .build();
+        assert!(slow.scan(1..4).unwrap().is_empty());
+
+        assert!(fast.clock.elapsed() < slow.clock.elapsed());
+    }
+    #[test]
+    fn set_led() {
+        use motors::{LedColor, XL_320::XL320, XL_430::XL430};
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.set_led::<XL320>(1, LedColor::Cyan).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x19, 1, 6));
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        assert_eq!(
+            c.set_led::<XL430>(1, LedColor::Green).unwrap_err().error,
+            ErrorType::InvalidValue
+        );
+        assert!(c.tx.sent().is_empty());
+    }
+    #[test]
+    fn go_to_position() {
+        use motors::{XL_320::XL320, XL_430::XL430};
+        let (timeout, interval) = (hal::time::MilliSecond(1000), hal::time::MilliSecond(10));
+
+        let mut c =
+            fake_controller_answering(&[(1, vec![]), (1, vec![0x00, 0x01]), (1, vec![0xFE, 0x01])]);
+        assert_eq!(
+            c.go_to_position::<XL320>(1, 512, 2, timeout, interval)
+                .unwrap(),
+            510
+        );
+        assert_eq!(c.rx.unread(), 0);
+
+        let mut answer = status_bytes(1, vec![]);
+        for _ in 0..10 {
+            answer.extend(status_bytes(1, vec![0x00, 0x01]));
         }
+        let mut c = fake_controller(&answer, false);
+        let timeout = hal::time::MilliSecond(50);
+        assert_eq!(
+            c.go_to_position::<XL320>(1, 512, 2, timeout, interval)
+                .unwrap_err()
+                .error,
+            ErrorType::NotInPosition
+        );
+
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        assert_eq!(
+            c.go_to_position::<XL320>(1, 512, 2, timeout, interval)
+                .unwrap_err()
+                .error,
+            ErrorType::Timeout { received: 0 }
+        );
+
+        // Extended position mode: -1 is one tick away from 0.
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![0xFF, 0xFF, 0xFF, 0xFF])]);
+        assert_eq!(
+            c.go_to_position::<XL430>(1, 0, 2, timeout, interval)
+                .unwrap(),
+            0xFFFF_FFFF
+        );
     }
-    fn random_error() -> Option<u8> {
-        let e = Range::new(0u8, 8).sample(&mut rand::thread_rng());
-        match e {
-            0 => None,
-            e => Some(e),
+    #[test]
+    fn shutdown_config() {
+        use motors::XL_320::XL320;
+
+        let mut c = fake_controller_answering(&[(1, vec![]), (1, vec![])]);
+        let config = HardwareError::OVERHEATING | HardwareError::INPUT_VOLTAGE;
+        c.set_shutdown_config::<XL320>(1, config).unwrap();
+        let mut expected = write_data_bytes(1, 0x18, 1, 0);
+        expected.extend(write_data_bytes(1, 0x12, 1, 0b110));
+        assert_eq!(c.tx.sent(), expected);
+    }
+    #[test]
+    fn ping_info() {
+        let mut c = fake_controller(&status_bytes(1, vec![0x24, 0x04, 0x2D]), false);
+        assert_eq!(
+            c.ping_info(1).unwrap(),
+            Some(PingInfo {
+                model_number: 1060,
+                firmware_version: 45,
+            })
+        );
+
+        let mut c = fake_controller(&[], false);
+        assert_eq!(c.ping_info(1).unwrap(), None);
+    }
+    #[test]
+    fn into_parts() {
+        let mut c = fake_controller(&status_bytes(1, vec![]), false);
+        c.enable_torque::<::motors::XL_320::XL320>(1).unwrap();
+
+        let (rx, tx, clock) = c.into_parts();
+        assert!(rx.unread() == 0);
+        assert_eq!(tx.sent(), write_data_bytes(1, 0x18, 1, 1));
+        assert!(clock.elapsed() > 0);
+    }
+    #[test]
+    fn share_config() {
+        let configured = ControllerV2Builder::new(
+            fake_controller(&[], false).rx,
+            fake_controller(&[], false).tx,
+            MockClock::new(),
+        )
+        .timeout(hal::time::MilliSecond(20))
+        .retries(3)
+        .baud_rate(BaudRate::B1M)
+        .status_return_level(StatusReturnLevel::PingAndRead)
+        .build();
+        let mut config = configured.config();
+        assert_eq!(config.timeout.0, 20);
+        assert!(!config.learn_status_return_level);
+        config.learn_status_return_level = true;
+        assert_eq!(config.retries, 3);
+
+        let mut c = fake_controller(&[], false);
+        c.set_config(config.clone());
+        assert_eq!(c.timeout.0, 20);
+        assert_eq!(c.retries, 3);
+        assert_eq!(c.baud_rate, Some(BaudRate::B1M));
+        assert_eq!(c.status_return_level, StatusReturnLevel::PingAndRead);
+        assert!(c.learn_status_return_level);
+        assert!(c.crc_check && c.eeprom_guard);
+        assert_eq!(format!("{:?}", c.config()), format!("{:?}", config));
+    }
+    #[test]
+    fn replace_serial() {
+        let mut c = fake_controller(&[], false);
+        c.set_echo_cancellation(true);
+        assert!(c.read_data(1, &::motors::XL_320::ID).is_err());
+
+        let other = fake_controller(&status_bytes(1, vec![1]), true);
+        let (_, old_tx) = c.replace_serial(other.rx, other.tx);
+        assert!(!old_tx.sent().is_empty());
+
+        assert_eq!(c.read_data(1, &::motors::XL_320::ID).unwrap(), 1);
+        assert!(c.echo_cancellation);
+    }
+    #[test]
+    fn is_moving() {
+        use motors::XL_430::XL430;
+
+        let mut c = fake_controller_answering(&[(1, vec![1]), (1, vec![0])]);
+        assert!(c.is_moving::<XL430>(1).unwrap());
+        assert!(!c.is_moving::<XL430>(1).unwrap());
+        assert_eq!(&c.tx.sent()[8..12], &[0x7A, 0x00, 0x01, 0x00]);
+    }
+    #[test]
+    fn read_model() {
+        let mut c = fake_controller_answering(&[(1, vec![0x5E, 0x01]), (2, vec![0x1E, 0x00])]);
+
+        assert_eq!(c.read_model(1).unwrap(), Model::XL320);
+        assert_eq!(&c.tx.sent()[8..12], &[0x00, 0x00, 0x02, 0x00]);
+        assert_eq!(c.read_model(2).unwrap(), Model::Unknown(30));
+    }
+    #[test]
+    fn decode_byte_stream() {
+        let mut stream = vec![0x00, 0xFF, 0x42];
+        stream.extend(status_bytes(1, vec![0x12, 0x34]));
+        stream.extend(status_bytes(2, vec![]));
+
+        let mut decoder = StatusPacketDecoder::new(true);
+        let packets: Vec<StatusPacket> = stream
+            .iter()
+            .filter_map(|&b| decoder.push(b).unwrap())
+            .collect();
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].id(), 1);
+        assert_eq!(packets[0].parameters(), &[0x12, 0x34]);
+        assert_eq!(packets[1].id(), 2);
+        assert_eq!(decoder.received(), 0);
+    }
+    #[test]
+    fn decode_invalid_crc() {
+        let mut bytes = status_bytes(1, vec![0x12]);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+
+        let mut decoder = StatusPacketDecoder::new(true);
+        let (head, tail) = bytes.split_at(last);
+        for &b in head {
+            assert!(decoder.push(b).unwrap().is_none());
         }
+        assert_eq!(
+            decoder.push(tail[0]).unwrap_err().error,
+            ErrorType::InvalidChecksum
+        );
+
+        let mut decoder = StatusPacketDecoder::new(false);
+        let status = bytes
+            .iter()
+            .filter_map(|&b| decoder.push(b).unwrap())
+            .next();
+        assert_eq!(status.unwrap().parameters(), &[0x12]);
+        assert!(decoder.crc_mismatch());
+
+        // The decoder is ready for the next packet after an error.
+        let mut decoder = StatusPacketDecoder::new(true);
+        assert!(bytes.iter().any(|&b| decoder.push(b).is_err()));
+        let answer = status_bytes(1, vec![]);
+        assert_eq!(
+            answer
+                .iter()
+                .filter_map(|&b| decoder.push(b).unwrap())
+                .count(),
+            1
+        );
     }
-    fn random_parameters() -> Vec<u8> {
-        let size: u8 = random();
-        let mut data = Vec::new();
-        for _ in 0..size {
-            data.push(random());
+    #[test]
+    fn eeprom_cache() {
+        use motors::XL_320;
+
+        let mut c = fake_controller_answering(&[
+            (1, vec![3]),
+            (1, vec![0x10, 0x00]),
+            (1, vec![0x11, 0x00]),
+            (1, vec![]),
+            (1, vec![1]),
+        ]);
+        c.set_eeprom_cache(true);
+
+        assert_eq!(c.read_data(1, &XL_320::BaudRate).unwrap(), 3);
+        let sent = c.tx.sent().len();
+        assert_eq!(c.read_data(1, &XL_320::BaudRate).unwrap(), 3);
+        assert_eq!(c.tx.sent().len(), sent);
+
+        // RAM registers are always read from the motor.
+        assert_eq!(c.read_data(1, &XL_320::PresentPosition).unwrap(), 0x10);
+        assert_eq!(c.read_data(1, &XL_320::PresentPosition).unwrap(), 0x11);
+
+        c.write_data(1, &XL_320::BaudRate, 1).unwrap();
+        assert_eq!(c.read_data(1, &XL_320::BaudRate).unwrap(), 1);
+
+        // A read of another length at the same address is not answered from the cache.
+        struct ModelNumberLow;
+        impl Register for ModelNumberLow {
+            fn address(&self) -> u16 {
+                0x00
+            }
+            fn length(&self) -> u16 {
+                1
+            }
+            fn area(&self) -> RegisterArea {
+                RegisterArea::Eeprom
+            }
         }
-        data
+        let mut c = fake_controller_answering(&[(1, vec![0x5E, 0x01]), (1, vec![0x5E])]);
+        c.set_eeprom_cache(true);
+        assert_eq!(c.read_data(1, &XL_320::ModelNumber).unwrap(), 350);
+        assert_eq!(c.read_data(1, &ModelNumberLow).unwrap(), 0x5E);
+    }
+    #[test]
+    fn invalidate_cache() {
+        use motors::XL_320;
+
+        let mut c = fake_controller_answering(&[(1, vec![3]), (2, vec![3]), (1, vec![2])]);
+        c.set_eeprom_cache(true);
+
+        assert_eq!(c.read_data(1, &XL_320::BaudRate).unwrap(), 3);
+        assert_eq!(c.read_data(2, &XL_320::BaudRate).unwrap(), 3);
+        c.invalidate_cache(1);
+        let sent = c.tx.sent().len();
+        assert_eq!(c.read_data(2, &XL_320::BaudRate).unwrap(), 3);
+        assert_eq!(c.tx.sent().len(), sent);
+        assert_eq!(c.read_data(1, &XL_320::BaudRate).unwrap(), 2);
+
+        // After set_id, the values cached for the new id are not the ones of the renamed motor.
+        let mut c = fake_controller_answering(&[
+            (2, vec![3]),
+            (1, vec![]),
+            (2, vec![]),
+            (2, vec![0x5E, 0x01, 0x1D]),
+            (2, vec![1]),
+        ]);
+        c.set_eeprom_cache(true);
+        assert_eq!(c.read_data(2, &XL_320::BaudRate).unwrap(), 3);
+        c.set_id::<XL_320::XL320>(1, 2).unwrap();
+        assert_eq!(c.read_data(2, &XL_320::BaudRate).unwrap(), 1);
+    }
+    #[test]
+    #[cfg(feature = "util")]
+    fn sweep() {
+        use motors::XL_320::XL320;
+
+        let mut answer = status_bytes(1, vec![]);
+        for &present in &[0x00, 0x0F, 0x1E, 0x0F, 0x01] {
+            answer.extend(status_bytes(1, vec![]));
+            answer.extend(status_bytes(1, vec![present, 0x00]));
+        }
+        answer.extend(status_bytes(1, vec![]));
+        let mut c = fake_controller(&answer, false);
+
+        assert_eq!(
+            c.sweep::<XL320>(1, 0, 30, 15, hal::time::MilliSecond(1))
+                .unwrap(),
+            vec![(0, 0x00), (15, 0x0F), (30, 0x1E), (15, 0x0F), (0, 0x01)]
+        );
+        let end = c.tx.sent().len();
+        assert_eq!(
+            &c.tx.sent()[end - 13..],
+            &write_data_bytes(1, 0x18, 1, 0)[..]
+        );
+
+        assert_eq!(
+            c.sweep::<XL320>(1, 0, 30, 0, hal::time::MilliSecond(1))
+                .unwrap_err()
+                .error,
+            ErrorType::InvalidValue
+        );
+    }
+    #[test]
+    fn discover() {
+        let mut c = fake_controller_answering(&[
+            (1, vec![0x5E, 0x01, 0x29]),
+            (3, vec![0x24, 0x04, 0x2D]),
+            (4, vec![0x1E, 0x00, 0x2D]),
+        ]);
+
+        assert_eq!(
+            c.discover().unwrap(),
+            vec![
+                (1, Model::XL320),
+                (3, Model::XL430),
+                (4, Model::Unknown(30)),
+            ]
+        );
+        let mut ping = [0; MAX_REGISTER_PACKET_LENGTH];
+        let length = encode_ping(BROADCAST_ID, &mut ping).unwrap();
+        assert_eq!(c.tx.sent(), &ping[..length]);
+        assert!(c.clock.elapsed() >= 3 * 253, "waits for all the id slots");
+        assert_eq!(c.timeout, TIMEOUT);
+
+        let mut c = fake_controller_answering(&[
+            (1, vec![0x5E, 0x01, 0x29]),
+            (2, vec![0x24, 0x04]),
+            (3, vec![0x24, 0x04, 0x2D]),
+        ]);
+        assert_eq!(
+            c.discover().unwrap(),
+            vec![(1, Model::XL320), (3, Model::XL430)]
+        );
+
+        let mut answer = status_bytes(1, vec![0x5E, 0x01, 0x29]);
+        answer.extend(&status_bytes(3, vec![0x24, 0x04, 0x2D])[..5]);
+        let mut c = fake_controller(&answer, false);
+        match c.discover().unwrap_err().error {
+            ErrorType::Timeout { received: 5 } => {}
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+    #[test]
+    fn motor_binding() {
+        use motors::{Motor, XL_430::XL430};
+
+        let motor = Motor::<XL430>::new(1);
+        let answer = status_bytes(1, vec![0x00, 0x08, 0x00, 0x00]);
+        let mut c = fake_controller(&answer, false);
+        assert_eq!(motor.read_position(&mut c).unwrap(), 180.0);
+        assert_eq!(&c.tx.sent()[8..12], &[0x84, 0x00, 0x04, 0x00]);
+
+        let answer = status_bytes(1, vec![]);
+        let mut c = fake_controller(&answer, false);
+        motor.write_position(&mut c, 90.0).unwrap();
+        assert_eq!(c.tx.sent(), write_data_bytes(1, 0x74, 4, 1024));
+    }
+    #[test]
+    fn read_hardware_error() {
+        use motors::XL_430::XL430;
+
+        let answer = StatusPacket {
+            id: 1,
+            _length: 5,
+            error_code: Some(0x80),
+            parameters: vec![0b0010_0100],
+        }
+        .to_bytes();
+
+        let mut c = fake_controller(&answer, false);
+        let e = c.read_hardware_error::<XL430>(1).unwrap();
+        assert_eq!(e, HardwareError::OVERHEATING | HardwareError::OVERLOAD);
+        assert_eq!(&c.tx.sent()[8..12], &[0x46, 0x00, 0x01, 0x00]);
+        assert!(!c.ignore_alert);
     }
-    // struct FakeRx;
-    // impl FakeRx {}
-    // impl hal::serial::Read<u8> for FakeRx {
-    //     type Error = !;
-    //     fn read(&mut self) -> nb::Result<u8, Self::Error> {
-    //         Ok(self.read())
-    //     }
-    // }
-    // struct FakeTx;
-    // impl hal::serial::Write<u8> for FakeTx {
-    //     type Error = !;
-    //     fn write(&mut self, _: u8) -> nb::Result<(), Self::Error> {
-    //         Ok(())
-    //     }
-    //     fn flush(&mut self) -> nb::Result<(), Self::Error> {
-    //         Ok(())
-    //     }
-    //     fn complete(&self) -> nb::Result<(), Self::Error> {
-    //         Ok(())
-    //     }
-    // }
 }
@@ -0,0 +1,173 @@
+//! Serial transport shared by several controllers (e.g. one per limb of a robot on a single RS-485 bus).
+//!
+//! ## Example
+//!
+//! ```ignore
+//! let bus = SharedBus::new(serial);
+//! let mut left_arm = dynamixel::with_protocol_v2(bus.clone(), bus.clone(), clock_left);
+//! let mut right_arm = dynamixel::with_protocol_v2(bus.clone(), bus.clone(), clock_right);
+//!
+//! // In each task, hold the bus during the whole transaction.
+//! let pos = {
+//!     let _bus = bus.lock();
+//!     left_arm.read_data(1, &XL_430::PresentPosition)
+//! };
+//! ```
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use hal;
+use nb;
+
+/// Serial transport implementing both `Read` and `Write`, shared between threads.
+///
+/// Clones share the same transport, so they can be given as RX and TX to several controllers.
+/// Each byte access is serialized, but the packets of two controllers can still be interleaved: `lock` must be held for the duration of each transaction (InstructionPacket and StatusPacket).
+pub struct SharedBus<T> {
+    serial: Arc<Mutex<T>>,
+    transaction: Arc<Mutex<()>>,
+}
+impl<T> Clone for SharedBus<T> {
+    fn clone(&self) -> SharedBus<T> {
+        SharedBus {
+            serial: Arc::clone(&self.serial),
+            transaction: Arc::clone(&self.transaction),
+        }
+    }
+}
+impl<T> SharedBus<T> {
+    /// Share the `serial` transport.
+    pub fn new(serial: T) -> SharedBus<T> {
+        SharedBus {
+            serial: Arc::new(Mutex::new(serial)),
+            transaction: Arc::new(Mutex::new(())),
+        }
+    }
+    /// Take the bus for a transaction, the other users block until the returned guard is dropped.
+    pub fn lock<'a>(&'a self) -> MutexGuard<'a, ()> {
+        self.transaction
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    fn serial<'a>(&'a self) -> MutexGuard<'a, T> {
+        // A user panicking in the middle of a transaction does not leave the transport itself in an invalid state.
+        self.serial
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+impl<T> hal::serial::Read<u8> for SharedBus<T>
+where
+    T: hal::serial::Read<u8>,
+{
+    type Error = T::Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.serial().read()
+    }
+}
+impl<T> hal::serial::Write<u8> for SharedBus<T>
+where
+    T: hal::serial::Write<u8>,
+{
+    type Error = T::Error;
+
+    fn write(&mut self, b: u8) -> nb::Result<(), Self::Error> {
+        self.serial().write(b)
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.serial().flush()
+    }
+    fn complete(&self) -> nb::Result<(), Self::Error> {
+        self.serial().complete()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::thread;
+
+    use super::*;
+    use hal::time::{MilliSecond, Time};
+    use motors::XL_320;
+
+    /// Motor answering a canned StatusPacket to each InstructionPacket.
+    #[derive(Default)]
+    struct Loopback {
+        answer: Vec<u8>,
+        pending: VecDeque<u8>,
+        sent: Vec<u8>,
+        instruction_length: usize,
+    }
+    impl hal::serial::Read<u8> for Loopback {
+        type Error = !;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            self.pending.pop_front().ok_or(nb::Error::WouldBlock)
+        }
+    }
+    impl hal::serial::Write<u8> for Loopback {
+        type Error = !;
+
+        fn write(&mut self, b: u8) -> nb::Result<(), Self::Error> {
+            self.sent.push(b);
+            self.instruction_length += 1;
+            // End of the read InstructionPacket
+            if self.instruction_length == 14 {
+                self.instruction_length = 0;
+                self.pending.extend(&self.answer);
+            }
+            Ok(())
+        }
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+        fn complete(&self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct Clock(::std::time::Instant);
+    impl Time for Clock {
+        fn now(&self) -> MilliSecond {
+            let elapsed = self.0.elapsed();
+            MilliSecond(elapsed.as_secs() as u32 * 1000 + elapsed.subsec_millis())
+        }
+    }
+
+    #[test]
+    fn concurrent_transactions() {
+        let bus = SharedBus::new(Loopback {
+            answer: vec![
+                0xFF, 0xFF, 0xFD, 0x00, 1, 6, 0, 0x55, 0, 0x10, 0x02, 0xCA, 0xBB,
+            ],
+            ..Loopback::default()
+        });
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let bus = bus.clone();
+                thread::spawn(move || {
+                    let mut c = ::ControllerV2Builder::new(
+                        bus.clone(),
+                        bus.clone(),
+                        Clock(::std::time::Instant::now()),
+                    )
+                    .timeout(MilliSecond(100))
+                    .build();
+
+                    for _ in 0..10 {
+                        let _bus = bus.lock();
+                        assert_eq!(c.read_data(1, &XL_320::PresentPosition).unwrap(), 0x0210);
+                    }
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.join().unwrap();
+        }
+        assert_eq!(bus.serial().sent.len(), 40 * 14);
+    }
+}
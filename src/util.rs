@@ -0,0 +1,106 @@
+//! Convenience routines built on top of the controller, e.g. to bring up a new motor.
+
+#[cfg(not(feature = "std"))]
+use alloc::Vec;
+
+use hal;
+
+use error::SerialError;
+use motors::MotorModel;
+use protocol::ControllerV2;
+use DynamixelError;
+
+impl<RX, TX, CLOCK, DE> ControllerV2<RX, TX, CLOCK, DE>
+where
+    TX: hal::serial::Write<u8>,
+    TX::Error: SerialError,
+    RX: hal::serial::Read<u8>,
+    RX::Error: SerialError,
+    CLOCK: hal::time::Time,
+    DE: hal::digital::OutputPin,
+{
+    /// Move motor `id` from the raw position `from` to `to` and back by `step`, and return the `(goal, present)` positions recorded at each step.
+    ///
+    /// The torque is enabled first and disabled at the end, even if the sweep failed. The present position is read `delay` after each goal position is written.
+    /// An invalid value error is returned if `step` is 0.
+    ///
+    /// *Note: This will send an InstructionPacket to the motor and block until the StatusPacket is received as reponse for each step.*
+    pub fn sweep<M>(
+        &mut self,
+        id: u8,
+        from: u32,
+        to: u32,
+        step: u32,
+        delay: hal::time::MilliSecond,
+    ) -> Result<Vec<(u32, u32)>, DynamixelError>
+    where
+        M: MotorModel,
+    {
+        if step == 0 {
+            return Err(DynamixelError::invalid_value());
+        }
+
+        let mut goals = sweep_positions(from, to, step);
+        let back: Vec<u32> = goals.iter().rev().skip(1).cloned().collect();
+        goals.extend(back);
+
+        self.enable_torque::<M>(id)?;
+
+        let mut positions = Vec::with_capacity(goals.len());
+        let mut swept = Ok(());
+        for goal in goals {
+            swept = self
+                .write_data(id, M::GOAL_POSITION, goal)
+                .and_then(|_| {
+                    self.wait(delay);
+                    self.read_data(id, M::PRESENT_POSITION)
+                })
+                .map(|present| positions.push((goal, present)));
+
+            if swept.is_err() {
+                break;
+            }
+        }
+
+        let disabled = self.disable_torque::<M>(id);
+        swept?;
+        disabled?;
+
+        Ok(positions)
+    }
+}
+
+/// Positions from `from` to `to` (both included) by `step`.
+fn sweep_positions(from: u32, to: u32, step: u32) -> Vec<u32> {
+    let mut positions = vec![from];
+    let mut position = from;
+
+    while position != to {
+        position = if from < to {
+            if to - position > step {
+                position + step
+            } else {
+                to
+            }
+        } else if position - to > step {
+            position - step
+        } else {
+            to
+        };
+        positions.push(position);
+    }
+
+    positions
+}
+
+#[cfg(test)]
+mod test {
+    use super::sweep_positions;
+
+    #[test]
+    fn positions() {
+        assert_eq!(sweep_positions(0, 10, 4), vec![0, 4, 8, 10]);
+        assert_eq!(sweep_positions(10, 2, 4), vec![10, 6, 2]);
+        assert_eq!(sweep_positions(5, 5, 1), vec![5]);
+    }
+}